@@ -4,12 +4,20 @@ use crate::*;
 impl Contract {
   // Executor: bAsset token contract
   pub fn deposit_collateal(&mut self, borrower: AccountId, amount: Balance) {
+    self.assert_collateral_token();
+    self.assert_not_paused(Operation::Deposit);
     let mut borrower_info: BorrowerInfo = self.get_borrower_info_map(&borrower);
 
     borrower_info.balance += amount;
     borrower_info.spendable += amount;
 
     self.add_borrower_info_map(&borrower, &borrower_info);
+
+    Event::DepositCollateral {
+      borrower: &borrower,
+      amount: U128::from(amount),
+    }
+    .emit();
   }
 
   // Executor: borrwer
@@ -30,6 +38,12 @@ impl Contract {
 
     self.add_borrower_info_map(&borrower, &borrower_info);
 
+    Event::WithdrawCollateral {
+      borrower: &borrower,
+      amount: U128::from(amount),
+    }
+    .emit();
+
     fungible_token::ft_transfer(
       borrower,
       U128::from(amount),
@@ -43,6 +57,7 @@ impl Contract {
   // Executor: overseer
   pub fn lock_collateral(&mut self, borrower: AccountId, amount: Balance) {
     self.assert_overseer();
+    self.assert_not_paused(Operation::Lock);
 
     let mut borrower_info: BorrowerInfo = self.get_borrower_info_map(&borrower);
 
@@ -56,10 +71,17 @@ impl Contract {
     borrower_info.spendable = borrower_info.spendable - amount;
 
     self.add_borrower_info_map(&borrower, &borrower_info);
+
+    Event::LockCollateral {
+      borrower: &borrower,
+      amount: U128::from(amount),
+    }
+    .emit();
   }
 
   pub fn unlock_collateral(&mut self, borrower: AccountId, amount: Balance) {
     self.assert_overseer();
+    self.assert_not_paused(Operation::Unlock);
 
     let mut borrower_info: BorrowerInfo = self.get_borrower_info_map(&borrower);
     let borrrowed_amount = borrower_info.balance - borrower_info.spendable;
@@ -72,16 +94,28 @@ impl Contract {
 
     borrower_info.spendable += amount;
     self.add_borrower_info_map(&borrower, &borrower_info);
+
+    Event::UnlockCollateral {
+      borrower: &borrower,
+      amount: U128::from(amount),
+    }
+    .emit();
   }
 
   // Executer: overseer
+  /// Sends locked collateral to the liquidation queue, which repays the loan of `borrower`
+  /// in the market `repay_address` and pays the bid fee to `fee_address`.
+  /// Returns the amount the queue took.
   pub fn liquidate_collateral(
     &mut self,
     liquidator: AccountId,
     borrower: AccountId,
     amount: Balance,
-  ) {
+    repay_address: AccountId,
+    fee_address: AccountId,
+  ) -> Promise {
     self.assert_overseer();
+    self.assert_not_paused(Operation::Liquidate);
 
     let mut borrower_info: BorrowerInfo = self.get_borrower_info_map(&borrower);
     let borrrowed_amount = borrower_info.balance - borrower_info.spendable;
@@ -93,11 +127,15 @@ impl Contract {
       );
     }
 
+    // restored in the callback for whatever the queue doesn't take
     borrower_info.balance = borrower_info.balance - amount;
     self.add_borrower_info_map(&borrower, &borrower_info);
 
-    let msg = serde_json::to_string(&{
-      // TODO: call liquidation. how?
+    let msg = serde_json::to_string(&BnearReceiverPayload {
+      liquidator: liquidator.clone(),
+      borrower: borrower.clone(),
+      repay_address: Some(repay_address),
+      fee_address: Some(fee_address),
     })
     .unwrap();
 
@@ -107,8 +145,54 @@ impl Contract {
       None,
       msg,
       &self.config.collateral_token,
+      1,
+      env::prepaid_gas() - env::used_gas() - CALLBACK_GAS - GAS_RESERVE,
+    )
+    .then(ext_self::callback_liquidate_collateral(
+      liquidator,
+      borrower,
+      U128::from(amount),
+      &env::current_account_id(),
       NO_DEPOSIT,
-      SINGLE_CALL_GAS,
-    );
+      CALLBACK_GAS,
+    ))
+  }
+
+  /// `ft_transfer_call` resolves to the amount the queue used, the refunded rest
+  /// is locked again
+  #[private]
+  pub fn callback_liquidate_collateral(
+    &mut self,
+    liquidator: AccountId,
+    borrower: AccountId,
+    amount: U128,
+  ) -> U128 {
+    assert_eq!(env::promise_results_count(), 1, "This is a callback method");
+
+    let liquidated_amount: Balance = match env::promise_result(0) {
+      PromiseResult::Successful(result) => std::cmp::min(
+        amount.0,
+        serde_json::from_slice::<U128>(&result).map_or(0, |used| used.0),
+      ),
+      _ => 0,
+    };
+
+    let refund_amount: Balance = amount.0 - liquidated_amount;
+    if refund_amount > 0 {
+      let mut borrower_info: BorrowerInfo = self.get_borrower_info_map(&borrower);
+      borrower_info.balance += refund_amount;
+      self.add_borrower_info_map(&borrower, &borrower_info);
+    }
+
+    if liquidated_amount > 0 {
+      Event::LiquidateCollateral {
+        liquidator: &liquidator,
+        borrower: &borrower,
+        amount: U128::from(liquidated_amount),
+      }
+      .emit();
+    }
+
+    U128::from(liquidated_amount)
   }
 }
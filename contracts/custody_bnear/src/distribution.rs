@@ -8,38 +8,42 @@ const REWARDS_THRESHOLD: Balance = 1_000_000u128; // TODO check decimal
 #[near_bindgen]
 impl Contract {
   // Executor: overseer
-  pub fn distribute_rewards(&self) {
+  pub fn distribute_rewards(&self) -> Promise {
     self.assert_overseer();
 
     ext_reward::get_accrued_rewards(
       env::current_account_id(),
       &self.config.reward_contract,
       NO_DEPOSIT,
-      SINGLE_CALL_GAS,
+      REWARD_QUERY_GAS,
     )
     .then(ext_self::callback_distribute_rewards(
       REWARDS_THRESHOLD,
       &env::current_account_id(),
       NO_DEPOSIT,
-      SINGLE_CALL_GAS,
-    ));
+      CLAIM_REWARDS_GAS + DISTRIBUTE_HOOK_GAS + CALLBACK_GAS,
+    ))
   }
 
-  // Executor: itself
-  pub fn distribute_hook(&self) {
+  // Executor: itself, once the rewards are claimed
+  #[private]
+  pub fn distribute_hook(&self) -> Promise {
+    self.swap_to_stable_denom();
+
     fungible_token::ft_balance_of(
       env::current_account_id(),
       &self.config.stable_coin_contract,
       NO_DEPOSIT,
-      SINGLE_CALL_GAS,
+      REWARD_QUERY_GAS,
     )
     .then(ext_self::callback_distribute_hook(
       &env::current_account_id(),
       NO_DEPOSIT,
-      SINGLE_CALL_GAS,
-    ));
+      TRANSFER_CALL_GAS + CALLBACK_GAS,
+    ))
   }
 
   // Executor: itself
+  // the reward contract pays in the stable coin, there is nothing to swap yet
   pub fn swap_to_stable_denom(&self) {}
 }
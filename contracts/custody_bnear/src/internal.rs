@@ -10,6 +10,14 @@ impl Contract {
     );
   }
 
+  pub(crate) fn assert_collateral_token(&self) {
+    assert_eq!(
+      env::predecessor_account_id(),
+      self.config.collateral_token,
+      "Can only be called by the collateral token"
+    );
+  }
+
   pub(crate) fn assert_overseer(&self) {
     assert_eq!(
       env::predecessor_account_id(),
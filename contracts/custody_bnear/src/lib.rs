@@ -1,25 +1,32 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::collections::LookupMap;
-use near_sdk::json_types::U128;
+use near_sdk::json_types::{ValidAccountId, U128};
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::{
     assert_one_yocto, env, ext_contract, near_bindgen, serde_json, AccountId, Balance, BlockHeight,
-    BorshStorageKey, Gas, PanicOnDefault, Promise, PromiseOrValue, PromiseResult,
+    BorshStorageKey, Gas, PanicOnDefault, Promise, PromiseOrValue, PromiseResult, StorageUsage,
 };
 
 use uint::construct_uint;
 
+use crate::events::Event;
 use crate::math::D128;
+use crate::pause::{Operation, PauseFlags};
 use crate::state::{BAssetInfo, BorrowerInfo, Collection, Config, State};
-use crate::utils::{ext_reward, ext_self, fungible_token};
+use crate::utils::{ext_reward, ext_self, fungible_token, BnearReceiverPayload};
 
+mod admin;
 mod collateral;
 mod distribution;
+mod events;
 mod fungible_token_handler;
 mod internal;
 mod math;
+mod migration;
 mod owner;
+mod pause;
 mod state;
+mod storage;
 #[cfg(test)]
 mod testing;
 mod utils;
@@ -29,6 +36,21 @@ const NO_DEPOSIT: Balance = 0;
 
 const SINGLE_CALL_GAS: Gas = 100_000_000_000_000;
 
+const CALLBACK_GAS: Gas = 10_000_000_000_000;
+
+// reward contract and stable coin views
+const REWARD_QUERY_GAS: Gas = 5_000_000_000_000;
+
+const CLAIM_REWARDS_GAS: Gas = 15_000_000_000_000;
+
+// stable coin sends the rewards to the overseer with a transfer call
+const TRANSFER_CALL_GAS: Gas = 25_000_000_000_000;
+
+const DISTRIBUTE_HOOK_GAS: Gas = REWARD_QUERY_GAS + TRANSFER_CALL_GAS + 2 * CALLBACK_GAS;
+
+// kept for the rest of a call that forwards its remaining gas
+const GAS_RESERVE: Gas = 10_000_000_000_000;
+
 construct_uint! {
     /// 256-bit unsigned integer.
     pub struct U256(4);
@@ -45,6 +67,7 @@ pub struct Contract {
     config: Config,
     state: State,
     collection: Collection,
+    account_storage_usage: StorageUsage,
 }
 
 #[near_bindgen]
@@ -67,7 +90,9 @@ impl Contract {
         );
 
         let config = Config {
+            guardian_id: owner_id.clone(),
             owner_id,
+            pending_owner_id: None,
             overseer_contract,
             collateral_token,
             market_contract,
@@ -77,16 +102,21 @@ impl Contract {
             basset_info,
         };
 
-        let state = State {};
+        let state = State {
+            paused: PauseFlags::default(),
+        };
 
         let collection = Collection {
             borrower_info_map: LookupMap::new(StorageKey::BorrowerInfo),
         };
 
-        Self {
+        let mut this = Self {
             config,
             state,
             collection,
-        }
+            account_storage_usage: 0,
+        };
+        this.measure_account_storage_usage();
+        this
     }
 }
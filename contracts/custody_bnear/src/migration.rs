@@ -0,0 +1,63 @@
+use crate::*;
+
+/// `Config` of the deployed custody, before the ownership transfer and the guardian
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OldConfig {
+  pub owner_id: AccountId,
+  pub overseer_contract: AccountId,
+  pub collateral_token: AccountId,
+  pub market_contract: AccountId,
+  pub reward_contract: AccountId,
+  pub liquidation_contract: AccountId,
+  pub stable_coin_contract: AccountId,
+  pub basset_info: BAssetInfo,
+}
+
+/// `State` of the deployed custody, it had no fields
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OldState {}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OldContract {
+  pub config: OldConfig,
+  pub state: OldState,
+  pub collection: Collection,
+}
+
+#[near_bindgen]
+impl Contract {
+  /// Migrates the deployed custody. The borrowers keep their balances and are
+  /// registered without a storage deposit; nothing is paused and the owner is
+  /// the guardian, as in `new`.
+  #[private]
+  #[init(ignore_state)]
+  pub fn migrate() -> Self {
+    let old: OldContract = env::state_read().expect("Old state doesn't exist");
+
+    let config = Config {
+      guardian_id: old.config.owner_id.clone(),
+      owner_id: old.config.owner_id,
+      pending_owner_id: None,
+      overseer_contract: old.config.overseer_contract,
+      collateral_token: old.config.collateral_token,
+      market_contract: old.config.market_contract,
+      reward_contract: old.config.reward_contract,
+      liquidation_contract: old.config.liquidation_contract,
+      stable_coin_contract: old.config.stable_coin_contract,
+      basset_info: old.config.basset_info,
+    };
+
+    let state = State {
+      paused: PauseFlags::default(),
+    };
+
+    let mut this = Self {
+      config,
+      state,
+      collection: old.collection,
+      account_storage_usage: 0,
+    };
+    this.measure_account_storage_usage();
+    this
+  }
+}
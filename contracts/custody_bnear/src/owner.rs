@@ -36,5 +36,7 @@ impl Contract {
     if let Some(basset_info) = basset_info {
       self.config.basset_info = basset_info;
     }
+
+    Event::UpdateConfig(&self.config).emit();
   }
 }
@@ -0,0 +1,52 @@
+use crate::*;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum Operation {
+  Deposit,
+  Lock,
+  Unlock,
+  Liquidate,
+}
+
+/// Operations halted by the guardian, `true` means paused
+#[derive(
+  BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq,
+)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PauseFlags {
+  pub deposit: bool,
+  pub lock: bool,
+  pub unlock: bool,
+  pub liquidate: bool,
+}
+
+impl PauseFlags {
+  pub(crate) fn get(&self, operation: Operation) -> bool {
+    match operation {
+      Operation::Deposit => self.deposit,
+      Operation::Lock => self.lock,
+      Operation::Unlock => self.unlock,
+      Operation::Liquidate => self.liquidate,
+    }
+  }
+
+  pub(crate) fn get_mut(&mut self, operation: Operation) -> &mut bool {
+    match operation {
+      Operation::Deposit => &mut self.deposit,
+      Operation::Lock => &mut self.lock,
+      Operation::Unlock => &mut self.unlock,
+      Operation::Liquidate => &mut self.liquidate,
+    }
+  }
+}
+
+impl Contract {
+  pub(crate) fn pause_flags(&self) -> &PauseFlags {
+    &self.state.paused
+  }
+
+  pub(crate) fn pause_flags_mut(&mut self) -> &mut PauseFlags {
+    &mut self.state.paused
+  }
+}
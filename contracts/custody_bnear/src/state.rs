@@ -4,6 +4,10 @@ use crate::*;
 #[serde(crate = "near_sdk::serde")]
 pub struct Config {
   pub owner_id: AccountId,
+  // proposed owner, set until it accepts the ownership
+  pub pending_owner_id: Option<AccountId>,
+  // can pause operations, only the owner can unpause them
+  pub guardian_id: AccountId,
   pub overseer_contract: AccountId,
   pub collateral_token: AccountId,
   pub market_contract: AccountId,
@@ -15,7 +19,9 @@ pub struct Config {
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy)]
 #[serde(crate = "near_sdk::serde")]
-pub struct State {}
+pub struct State {
+  pub paused: PauseFlags,
+}
 
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct Collection {
@@ -37,20 +43,18 @@ pub struct BorrowerInfo {
   pub spendable: Balance,
 }
 
-#[near_bindgen]
 impl Contract {
-  pub fn add_borrower_info_map(&mut self, key: &String, value: &BorrowerInfo) {
+  pub(crate) fn add_borrower_info_map(&mut self, key: &String, value: &BorrowerInfo) {
     self.collection.borrower_info_map.insert(&key, value);
   }
+}
 
+#[near_bindgen]
+impl Contract {
   pub fn get_borrower_info_map(&self, key: &String) -> BorrowerInfo {
     match self.collection.borrower_info_map.get(&key) {
-      Some(value) => {
-        let log_message = format!("Value from LookupMap is {:?}", value.clone());
-        env::log(log_message.as_bytes());
-        value
-      }
-      None => env::panic("".as_bytes()),
+      Some(value) => value,
+      None => env::panic(format!("The account {} is not registered", key).as_bytes()),
     }
   }
 }
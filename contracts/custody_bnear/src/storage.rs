@@ -0,0 +1,134 @@
+use crate::*;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StorageBalance {
+  pub total: U128,
+  pub available: U128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StorageBalanceBounds {
+  pub min: U128,
+  pub max: Option<U128>,
+}
+
+#[near_bindgen]
+impl Contract {
+  /// Registers `account_id` (or the caller) with an empty collateral entry.
+  /// Storage is a fixed cost, so any deposit above the minimum is refunded.
+  #[payable]
+  #[allow(unused_variables)]
+  pub fn storage_deposit(
+    &mut self,
+    account_id: Option<ValidAccountId>,
+    registration_only: Option<bool>,
+  ) -> StorageBalance {
+    let amount: Balance = env::attached_deposit();
+    let account_id: AccountId = account_id
+      .map(|a| a.into())
+      .unwrap_or_else(env::predecessor_account_id);
+
+    if self.collection.borrower_info_map.get(&account_id).is_some() {
+      env::log(b"The account is already registered, refunding the deposit");
+      if amount > 0 {
+        Promise::new(env::predecessor_account_id()).transfer(amount);
+      }
+    } else {
+      let min_balance = self.storage_balance_bounds().min.0;
+      if amount < min_balance {
+        env::panic(b"The attached deposit is less than the minimum storage balance");
+      }
+
+      self.internal_register_account(&account_id);
+      let refund = amount - min_balance;
+      if refund > 0 {
+        Promise::new(env::predecessor_account_id()).transfer(refund);
+      }
+    }
+
+    self.internal_storage_balance_of(&account_id).unwrap()
+  }
+
+  /// Nothing is ever available to withdraw since storage is a fixed cost.
+  #[payable]
+  pub fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+    assert_one_yocto();
+    let account_id = env::predecessor_account_id();
+    match self.internal_storage_balance_of(&account_id) {
+      Some(storage_balance) => {
+        if amount.map(|a| a.0).unwrap_or(0) > 0 {
+          env::panic(b"The amount is greater than the available storage balance");
+        }
+        storage_balance
+      }
+      None => env::panic(format!("The account {} is not registered", &account_id).as_bytes()),
+    }
+  }
+
+  /// Removes the caller's entry and refunds the storage deposit.
+  /// Fails while the account still has collateral in custody.
+  #[payable]
+  #[allow(unused_variables)]
+  pub fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+    assert_one_yocto();
+    let account_id = env::predecessor_account_id();
+    match self.collection.borrower_info_map.get(&account_id) {
+      Some(borrower_info) => {
+        if borrower_info.balance != 0 {
+          env::panic(b"Can't unregister the account with an open position");
+        }
+        self.collection.borrower_info_map.remove(&account_id);
+        Promise::new(account_id).transfer(self.storage_balance_bounds().min.0 + 1);
+        true
+      }
+      None => {
+        env::log(format!("The account {} is not registered", &account_id).as_bytes());
+        false
+      }
+    }
+  }
+
+  pub fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+    let required_storage_balance =
+      Balance::from(self.account_storage_usage) * env::storage_byte_cost();
+    StorageBalanceBounds {
+      min: required_storage_balance.into(),
+      max: Some(required_storage_balance.into()),
+    }
+  }
+
+  pub fn storage_balance_of(&self, account_id: ValidAccountId) -> Option<StorageBalance> {
+    self.internal_storage_balance_of(account_id.as_ref())
+  }
+}
+
+impl Contract {
+  pub(crate) fn measure_account_storage_usage(&mut self) {
+    let initial_storage_usage = env::storage_usage();
+    let tmp_account_id = "a".repeat(64);
+    self.internal_register_account(&tmp_account_id);
+    self.account_storage_usage = env::storage_usage() - initial_storage_usage;
+    self.collection.borrower_info_map.remove(&tmp_account_id);
+  }
+
+  fn internal_register_account(&mut self, account_id: &AccountId) {
+    let borrower_info = BorrowerInfo {
+      balance: 0,
+      spendable: 0,
+    };
+    self.add_borrower_info_map(account_id, &borrower_info);
+  }
+
+  fn internal_storage_balance_of(&self, account_id: &AccountId) -> Option<StorageBalance> {
+    if self.collection.borrower_info_map.get(account_id).is_some() {
+      Some(StorageBalance {
+        total: self.storage_balance_bounds().min,
+        available: 0.into(),
+      })
+    } else {
+      None
+    }
+  }
+}
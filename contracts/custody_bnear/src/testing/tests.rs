@@ -1,5 +1,5 @@
 use near_sdk::test_utils::{accounts, VMContextBuilder};
-use near_sdk::{testing_env, MockedBlockchain};
+use near_sdk::{testing_env, MockedBlockchain, RuntimeFeesConfig, VMConfig};
 
 use crate::*;
 
@@ -25,3 +25,169 @@ fn setup_contract() -> (VMContextBuilder, Contract) {
   );
   (context, contract)
 }
+
+#[test]
+fn proper_storage_deposit() {
+  let (mut context, mut contract) = setup_contract();
+  let min = contract.storage_balance_bounds().min;
+
+  testing_env!(context
+    .predecessor_account_id(accounts(1))
+    .attached_deposit(min.0)
+    .build());
+  contract.storage_deposit(None, None);
+
+  assert_eq!(
+    BorrowerInfo {
+      balance: 0,
+      spendable: 0,
+    },
+    contract.get_borrower(accounts(1).to_string())
+  );
+}
+
+#[test]
+#[should_panic(expected = "Can't unregister the account with an open position")]
+fn storage_unregister_with_collateral() {
+  let (mut context, mut contract) = setup_contract();
+  let min = contract.storage_balance_bounds().min;
+
+  testing_env!(context
+    .predecessor_account_id(accounts(1))
+    .attached_deposit(min.0)
+    .build());
+  contract.storage_deposit(None, None);
+  contract.add_borrower_info_map(
+    &accounts(1).to_string(),
+    &BorrowerInfo {
+      balance: 100,
+      spendable: 100,
+    },
+  );
+
+  testing_env!(context.attached_deposit(1).build());
+  contract.storage_unregister(None);
+}
+
+#[test]
+#[should_panic(expected = "Deposit is paused")]
+fn deposit_collateral_while_paused() {
+  let (mut context, mut contract) = setup_contract();
+
+  testing_env!(context
+    .predecessor_account_id(ValidAccountId::try_from("owner").unwrap())
+    .attached_deposit(1)
+    .build());
+  contract.pause(Operation::Deposit);
+  assert_eq!(true, contract.get_state().paused.deposit);
+
+  testing_env!(context
+    .predecessor_account_id(ValidAccountId::try_from("collateral").unwrap())
+    .build());
+  contract.deposit_collateal(accounts(1).to_string(), 100);
+}
+
+#[test]
+#[should_panic(expected = "Can only be called by the collateral token")]
+fn deposit_collateral_by_non_token() {
+  let (mut context, mut contract) = setup_contract();
+
+  testing_env!(context.predecessor_account_id(accounts(1)).build());
+  contract.deposit_collateal(accounts(1).to_string(), 100);
+}
+
+#[test]
+fn relock_collateral_refunded_by_liquidation() {
+  let (mut context, mut contract) = setup_contract();
+  let borrower: AccountId = accounts(1).to_string();
+  // 100 deposited and locked, 40 sent to the liquidation queue
+  contract.add_borrower_info_map(
+    &borrower,
+    &BorrowerInfo {
+      balance: 60,
+      spendable: 0,
+    },
+  );
+
+  testing_env!(
+    context
+      .predecessor_account_id(accounts(0))
+      .current_account_id(accounts(0))
+      .build(),
+    VMConfig::default(),
+    RuntimeFeesConfig::default(),
+    Default::default(),
+    vec![PromiseResult::Successful(b"\"30\"".to_vec())]
+  );
+
+  let liquidated = contract.callback_liquidate_collateral(
+    AccountId::from("liquidator"),
+    borrower.clone(),
+    U128::from(40u128),
+  );
+  assert_eq!(U128::from(30u128), liquidated);
+  assert_eq!(
+    BorrowerInfo {
+      balance: 70,
+      spendable: 0,
+    },
+    contract.get_borrower_info_map(&borrower)
+  );
+}
+
+#[test]
+#[should_panic(expected = "Can only be called by the overseer")]
+fn distribute_rewards_by_non_overseer() {
+  let (mut context, contract) = setup_contract();
+
+  testing_env!(context.predecessor_account_id(accounts(1)).build());
+  contract.distribute_rewards();
+}
+
+#[test]
+fn migrate_from_deployed_custody() {
+  let mut context = VMContextBuilder::new();
+  testing_env!(context.build());
+
+  let mut borrower_info_map = LookupMap::new(StorageKey::BorrowerInfo);
+  borrower_info_map.insert(
+    &accounts(1).to_string(),
+    &BorrowerInfo {
+      balance: 100,
+      spendable: 40,
+    },
+  );
+  env::state_write(&migration::OldContract {
+    config: migration::OldConfig {
+      owner_id: AccountId::from("owner"),
+      overseer_contract: AccountId::from("overseer"),
+      collateral_token: AccountId::from("collateral"),
+      market_contract: AccountId::from("market"),
+      reward_contract: AccountId::from("reward"),
+      liquidation_contract: AccountId::from("liquidation"),
+      stable_coin_contract: AccountId::from("stable_coin"),
+      basset_info: BAssetInfo {
+        name: String::from("name"),
+        symbol: String::from("symbol"),
+        decimals: 8,
+      },
+    },
+    state: migration::OldState {},
+    collection: Collection { borrower_info_map },
+  });
+
+  let contract = Contract::migrate();
+  let config = contract.get_config();
+  assert_eq!(AccountId::from("owner"), config.guardian_id);
+  assert!(config.pending_owner_id.is_none());
+  assert_eq!(PauseFlags::default(), contract.get_state().paused);
+  assert_eq!(
+    BorrowerInfo {
+      balance: 100,
+      spendable: 40,
+    },
+    contract.get_borrower(accounts(1).to_string())
+  );
+  assert!(contract.storage_balance_of(accounts(1)).is_some());
+  assert_ne!(0, contract.account_storage_usage);
+}
@@ -19,19 +19,39 @@ pub trait FungibleToken {
 #[ext_contract(ext_reward)]
 pub trait RewardContract {
   fn get_accrued_rewards(&self, address: AccountId) -> U128;
+
+  fn claim_rewards(&mut self, recipient: Option<AccountId>);
 }
 
 #[ext_contract(ext_self)]
 pub trait Contract {
-  fn callback_distribute_rewards(&mut self, REWARDS_THRESHOLD: Balance);
+  fn callback_distribute_rewards(&mut self, rewards_threshold: Balance);
+  fn distribute_hook(&self) -> Promise;
   fn callback_distribute_hook(&self);
+  fn callback_liquidate_collateral(
+    &mut self,
+    liquidator: AccountId,
+    borrower: AccountId,
+    amount: U128,
+  ) -> U128;
+}
+
+/// `msg` of the collateral sent to the liquidation queue
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BnearReceiverPayload {
+  pub liquidator: AccountId,
+  // the proceeds repay the loan of this borrower
+  pub borrower: AccountId,
+  pub repay_address: Option<AccountId>,
+  pub fee_address: Option<AccountId>,
 }
 
 // TODO: need to move to each files(ex. borrow.ts, deposit.ts, etc )?
 #[near_bindgen]
 impl Contract {
   #[private]
-  fn callback_distribute_rewards(&mut self, REWARDS_THRESHOLD: Balance) {
+  pub fn callback_distribute_rewards(&mut self, rewards_threshold: Balance) {
     assert_eq!(env::promise_results_count(), 1, "This is a callback method");
 
     match env::promise_result(0) {
@@ -42,16 +62,28 @@ impl Contract {
       PromiseResult::Successful(result) => {
         let accrued_rewards: Balance = near_sdk::serde_json::from_slice::<U128>(&result).unwrap().0;
 
-        if accrued_rewards < REWARDS_THRESHOLD {
+        if accrued_rewards < rewards_threshold {
           return;
         }
 
-        self.swap_to_stable_denom();
+        ext_reward::claim_rewards(
+          None,
+          &self.config.reward_contract,
+          NO_DEPOSIT,
+          CLAIM_REWARDS_GAS,
+        )
+        .then(ext_self::distribute_hook(
+          &env::current_account_id(),
+          NO_DEPOSIT,
+          DISTRIBUTE_HOOK_GAS,
+        ));
       }
     }
   }
 
-  fn callback_distribute_hook(&self) {
+  /// Sends the stable coin balance to the overseer, it funds the interest buffer
+  #[private]
+  pub fn callback_distribute_hook(&self) {
     assert_eq!(env::promise_results_count(), 1, "This is a callback method");
 
     match env::promise_result(0) {
@@ -63,13 +95,15 @@ impl Contract {
         let reward_amount: Balance = near_sdk::serde_json::from_slice::<U128>(&result).unwrap().0;
 
         if reward_amount != 0 {
-          fungible_token::ft_transfer(
+          // the overseer accounts the rewards of each custody
+          fungible_token::ft_transfer_call(
             self.config.overseer_contract.clone(),
             U128::from(reward_amount),
             None,
+            String::from("\"collateral_rewards\""),
             &self.config.stable_coin_contract,
-            NO_DEPOSIT,
-            SINGLE_CALL_GAS,
+            1,
+            TRANSFER_CALL_GAS,
           );
         }
       }
@@ -1,27 +1,70 @@
 use crate::*;
 
+use flux_sdk::consts::GAS_BASE_TRANSFER;
+
 #[near_bindgen]
 impl Contract {
     /// Overseer executes the liquidation providing a whitelisted collateral(bNEAR).
-    /// This operation returns a repay_amount based on the available bids on each
-    /// premium slot, consuming bids from lowest to higher premium slots
+    /// The loan of borrower in the market at repay_address is queried first, so
+    /// no more bids are consumed than the loan needs.
+    /// The returned promise resolves to the collateral left unused
     pub(crate) fn on_receive_execute_liquidation(
         &mut self,
         sender: AccountId,
         liquidator: AccountId,
+        borrower: AccountId,
         repay_address: AccountId,
         fee_address: AccountId,
         amount: U128,
-    ) {
+    ) -> Promise {
+        self.assert_not_paused(Operation::Liquidate);
         self.internal_update_price_response();
 
+        // only collateral token custody can execute liquidations
+        assert_eq!(self.config.custody_contract, sender, "Unauthorized: only custody contract can execute liquidations");
+
+        let callback_gas: Gas = env::prepaid_gas() - env::used_gas() - QUERY_GAS - GAS_RESERVE;
+        ext_market::get_loan_amount(
+            borrower.clone(),
+            None,
+            // Near params
+            &repay_address,
+            0,
+            QUERY_GAS,
+        ).then(ext_self::callback_query_loan_amount(
+            liquidator,
+            borrower,
+            repay_address,
+            fee_address,
+            amount,
+            // Near params
+            &env::current_account_id(),
+            0,
+            callback_gas,
+        ))
+    }
+
+    /// This operation returns a repay_amount based on the available bids on each
+    /// premium slot, consuming bids from lowest to higher premium slots until the
+    /// collateral is sold or the loan is covered.
+    /// repay_amount repays the loan of borrower in the market at repay_address
+    pub(crate) fn internal_execute_liquidation(
+        &mut self,
+        liquidator: AccountId,
+        borrower: AccountId,
+        repay_address: AccountId,
+        fee_address: AccountId,
+        amount: U128,
+        loan_amount: u128,
+    ) -> PromiseOrValue<U128> {
         let config: Config = self.config.clone();
         let collateral_info: CollateralInfo = config.collateral_info;
         let available_bids: u128 = self.total_bids.0;
+        let price: D128 = self.last_price_response.price;
 
-        // only collateral token custody can execute liquidations
-        assert_eq!(config.custody_contract, sender, "Unauthorized: only custody contract can execute liquidations");
-
+        // the fees are taken from the bids on top of the repaid loan
+        let mut remaining_stable: D128 =
+            D128::new(loan_amount * DECIMAL) / (D128::one() - config.bid_fee - config.liquidator_fee);
         let mut remaining_collateral_to_liquidate: u128 = amount.0;
         let mut repay_amount: u128 = 0;
         let mut filled: bool = false;
@@ -34,39 +77,79 @@ impl Contract {
                 continue;
             };
 
+            // collateral the rest of the loan pays for at the premium of this pool
+            let premium_price: D128 = price * (D128::one() - bid_pool.premium_rate);
+            let pool_collateral_to_liquidate: u128 = std::cmp::min(
+                remaining_collateral_to_liquidate,
+                (remaining_stable / premium_price).as_u128(),
+            );
+            if pool_collateral_to_liquidate == 0 {
+                filled = true;
+                break;
+            }
+
             let (pool_repay_amount, pool_liquidated_collateral) = self.internal_execute_pool_liquidation(
                 &mut bid_pool,
                 slot,
-                remaining_collateral_to_liquidate,
-                self.last_price_response.price,
+                pool_collateral_to_liquidate,
+                price,
                 &mut filled,
             );
 
             self.internal_store_bid_pool(slot, &bid_pool);
 
             repay_amount += pool_repay_amount;
+            remaining_collateral_to_liquidate -= pool_liquidated_collateral;
+            remaining_stable = if remaining_stable > D128::new(pool_repay_amount * DECIMAL) {
+                remaining_stable - pool_repay_amount
+            } else {
+                D128::zero()
+            };
 
             if filled {
-                remaining_collateral_to_liquidate = 0;
                 break;
-            } else {
-                remaining_collateral_to_liquidate -= pool_liquidated_collateral;
             }
         }
 
-        assert_eq!(remaining_collateral_to_liquidate, 0, "Not enough bids to execute this liquidation");
+        assert!(filled, "Not enough bids to execute this liquidation");
+
+        if repay_amount == 0 {
+            env::log(format!("No loan of {} to repay in {}", borrower, repay_address).as_bytes());
+            return PromiseOrValue::Value(amount);
+        }
 
         self.total_bids = (available_bids - repay_amount).into();
 
         let bid_fee: D128 = repay_amount * config.bid_fee;
         let liquidator_fee: D128 = repay_amount * config.liquidator_fee;
         let repay_amount: D128 = repay_amount - bid_fee - liquidator_fee;
-        
-        fungible_token_transfer(
+
+        Event::ExecuteLiquidation {
+            liquidator: &liquidator,
+            collateral_amount: (amount.0 - remaining_collateral_to_liquidate).into(),
+            repay_amount: repay_amount.as_u128().into(),
+            bid_fee: bid_fee.as_u128().into(),
+            liquidator_fee: liquidator_fee.as_u128().into(),
+        }.emit();
+
+        let repay_msg: String = serde_json::json!({
+            "repay_stable": { "borrower": borrower }
+        }).to_string();
+        let repay_transfer: Promise = fungible_token_transfer_call(
             config.stable_coin_contract.clone(), 
             repay_address, 
-            repay_amount.as_u128()
-        );
+            repay_amount.as_u128(),
+            repay_msg,
+        )
+        .then(ext_self::callback_execute_liquidation(
+            borrower,
+            repay_amount.as_u128().into(),
+            remaining_collateral_to_liquidate.into(),
+            // Near params
+            &env::current_account_id(),
+            0,
+            GAS_BASE_TRANSFER + 5_000_000_000_000,
+        ));
 
         if bid_fee != D128::zero() {
             fungible_token_transfer(
@@ -83,12 +166,15 @@ impl Contract {
                 liquidator_fee.as_u128()
             );
         }
+
+        repay_transfer.into()
     }
 
     /// callback on transfer stable coin.
     /// Stable asset is submitted to create a bid record. If available bids for the collateral is under
     /// the threshold, the bid is activated. Bids are not used for liquidations until activated
     pub(crate) fn on_receive_submit_bid(&mut self, bidder: AccountId, premium_slot: u8, amount: U128) {
+        self.assert_not_paused(Operation::Bid);
         self.internal_update_price_response();
         
         let config = self.config.clone();
@@ -130,5 +216,7 @@ impl Contract {
         };
 
         self.internal_store_bid(bid_idx.into(), &bid);
+
+        Event::SubmitBid(&bid).emit();
     }
 }
\ No newline at end of file
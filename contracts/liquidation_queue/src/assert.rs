@@ -5,9 +5,9 @@ impl Contract{
     pub(crate) fn assert_owner(&self) {
         assert_eq!(
             env::predecessor_account_id(),
-            self.config.owner,
+            self.config.owner_id,
             "This method can only be called by {}",
-            self.config.owner
+            self.config.owner_id
         );
     }
 }
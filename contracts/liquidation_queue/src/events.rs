@@ -0,0 +1,64 @@
+use crate::*;
+
+const EVENT_STANDARD: &str = "money_market";
+const EVENT_STANDARD_VERSION: &str = "1.0.0";
+
+/// NEP-297 events of the liquidation queue, logged as `EVENT_JSON:{..}`
+#[derive(Serialize)]
+#[serde(
+    crate = "near_sdk::serde",
+    tag = "event",
+    content = "data",
+    rename_all = "snake_case"
+)]
+pub(crate) enum Event<'a> {
+    SubmitBid(&'a Bid),
+    ActivateBids {
+        bidder: &'a AccountId,
+        amount: U128,
+    },
+    RetractBid {
+        bidder: &'a AccountId,
+        bid_idx: U128,
+        amount: U128,
+    },
+    ClaimLiquidations {
+        bidder: &'a AccountId,
+        amount: U128,
+    },
+    ExecuteLiquidation {
+        liquidator: &'a AccountId,
+        collateral_amount: U128,
+        repay_amount: U128,
+        bid_fee: U128,
+        liquidator_fee: U128,
+    },
+    Pause {
+        operation: Operation,
+    },
+    Unpause {
+        operation: Operation,
+    },
+    UpdateConfig(&'a Config),
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<'a> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event: &'a Event<'a>,
+}
+
+impl Event<'_> {
+    pub(crate) fn emit(&self) {
+        let log = EventLog {
+            standard: EVENT_STANDARD,
+            version: EVENT_STANDARD_VERSION,
+            event: self,
+        };
+        let json = serde_json::to_string(&log).unwrap();
+        env::log(format!("EVENT_JSON:{}", json).as_bytes());
+    }
+}
@@ -2,24 +2,38 @@ use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::serde::{Serialize, Deserialize};
 use near_sdk::json_types::{ValidAccountId, U64, U128};
 use near_sdk::collections::{LookupMap, UnorderedMap, UnorderedSet};
-use near_sdk::{env, near_bindgen, serde_json, assert_one_yocto, BorshStorageKey, AccountId, Balance, PanicOnDefault, PromiseOrValue, Promise};
+use near_sdk::{env, near_bindgen, serde_json, assert_one_yocto, BorshStorageKey, AccountId, Balance, Gas, PanicOnDefault, PromiseOrValue, Promise, PromiseResult};
 use math::{D128, DECIMAL};
-use utils::{fungible_token_transfer, fungible_token_transfer_call, requester, ext_self};
+use utils::{fungible_token_transfer, fungible_token_transfer_call, requester, ext_market, ext_self};
 use assert::*;
+use events::Event;
+use pause::{Operation, PauseFlags};
 use std::convert::TryInto;
 
+mod admin;
 mod api;
 mod assert;
+mod events;
 mod internal;
 mod math;
+mod migration;
 mod owner;
+mod pause;
 mod state;
+#[cfg(test)]
+mod testing;
 mod token_receiver;
 mod utils;
 mod views;
 
 const SECOND_TO_NANO: u64 = 1_000_000_000;
 
+// market is queried for the loan of a borrower before a liquidation
+const QUERY_GAS: Gas = 10_000_000_000_000;
+
+// kept for the rest of a call that forwards its remaining gas
+const GAS_RESERVE: Gas = 10_000_000_000_000;
+
 #[derive(BorshStorageKey, BorshSerialize)]
 pub enum StorageKeys {
     Indexer,
@@ -78,7 +92,11 @@ pub struct CollateralInfo {
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(crate = "near_sdk::serde")]
 pub struct Config {
-    pub owner: AccountId,
+    pub owner_id: AccountId,
+    // can pause operations, only the owner can unpause them
+    pub guardian_id: AccountId,
+    // proposed owner, set until it accepts the ownership
+    pub pending_owner_id: Option<AccountId>,
     pub stable_coin_contract: AccountId,
     pub requester_contract: AccountId,
     pub oracle_payment_token: AccountId,
@@ -93,6 +111,15 @@ pub struct Config {
     pub collateral_info: CollateralInfo,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct State {
+    pub bid_idx: U128,
+    pub total_bids: U128,
+    pub last_price_response: PriceResponse,
+    pub paused: PauseFlags,
+}
+
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct Contract {
@@ -105,6 +132,7 @@ pub struct Contract {
     bid_idx: U128,
     total_bids: U128,
     last_price_response: PriceResponse,
+    paused: PauseFlags,
 }
 
 #[near_bindgen]
@@ -126,7 +154,9 @@ impl Contract {
     ) -> Self {
         let mut instance = Self{
             config: Config {
-                owner: owner.into(),
+                guardian_id: owner.clone().into(),
+                owner_id: owner.into(),
+                pending_owner_id: None,
                 stable_coin_contract: stable_coin_contract.into(),
                 requester_contract: requester_contract.into(),
                 oracle_payment_token: oracle_payment_token.into(),
@@ -146,6 +176,7 @@ impl Contract {
             bid_idx: U128(1),
             total_bids: U128(0),
             last_price_response: PriceResponse{price: D128::one(), last_updated_at: env::block_timestamp()},
+            paused: PauseFlags::default(),
         };
         // Updates initial price
         instance.internal_update_price_response();
@@ -210,6 +241,11 @@ impl Contract {
         }
 
         self.total_bids = available_bids;
+
+        Event::ActivateBids {
+            bidder: &bidder,
+            amount: total_activated_amount,
+        }.emit();
     }
 
     /// Bid owners can withdraw the ramaning bid amount at any time
@@ -288,6 +324,12 @@ impl Contract {
             refund_amount.into()
         };
 
+        Event::RetractBid {
+            bidder: &bidder,
+            bid_idx,
+            amount: withdraw_amount,
+        }.emit();
+
         fungible_token_transfer(self.config.stable_coin_contract.clone(), bidder, withdraw_amount.0);
     }
 
@@ -360,6 +402,11 @@ impl Contract {
             }
         }
         if claim_amount != 0 {
+            Event::ClaimLiquidations {
+                bidder: &bidder,
+                amount: claim_amount.into(),
+            }.emit();
+
             fungible_token_transfer(
                 self.config.collateral_info.bnear_contract.clone(), 
                 bidder, 
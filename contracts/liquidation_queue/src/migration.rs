@@ -0,0 +1,68 @@
+use crate::*;
+
+/// `Config` of the deployed queue, before the ownership transfer and the guardian
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OldConfig {
+    pub owner: AccountId,
+    pub stable_coin_contract: AccountId,
+    pub requester_contract: AccountId,
+    pub oracle_payment_token: AccountId,
+    pub overseer_contract: AccountId,
+    pub custody_contract: AccountId,
+    pub safe_ratio: D128,
+    pub bid_fee: D128,
+    pub liquidator_fee: D128,
+    pub liquidation_threshold: Balance,
+    pub waiting_period: u64,
+    pub collateral_info: CollateralInfo,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OldContract {
+    pub config: OldConfig,
+    pub bids_indexer_by_user: LookupMap<AccountId, UnorderedSet<U128>>,
+    pub bids: LookupMap<U128, Bid>,
+    pub bid_pools: UnorderedMap<u8, BidPool>,
+    pub epoch_scale_sum: LookupMap<(u8, U128, U128), D128>,
+    pub bid_idx: U128,
+    pub total_bids: U128,
+    pub last_price_response: PriceResponse,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Migrates the deployed queue. The bids and pools are kept as they are,
+    /// nothing is paused and the owner is the guardian, as in `new`.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let old: OldContract = env::state_read().expect("Old state doesn't exist");
+
+        Self {
+            config: Config {
+                guardian_id: old.config.owner.clone(),
+                owner_id: old.config.owner,
+                pending_owner_id: None,
+                stable_coin_contract: old.config.stable_coin_contract,
+                requester_contract: old.config.requester_contract,
+                oracle_payment_token: old.config.oracle_payment_token,
+                overseer_contract: old.config.overseer_contract,
+                custody_contract: old.config.custody_contract,
+                safe_ratio: old.config.safe_ratio,
+                bid_fee: old.config.bid_fee,
+                liquidator_fee: old.config.liquidator_fee,
+                liquidation_threshold: old.config.liquidation_threshold,
+                waiting_period: old.config.waiting_period,
+                collateral_info: old.config.collateral_info,
+            },
+            bids_indexer_by_user: old.bids_indexer_by_user,
+            bids: old.bids,
+            bid_pools: old.bid_pools,
+            epoch_scale_sum: old.epoch_scale_sum,
+            bid_idx: old.bid_idx,
+            total_bids: old.total_bids,
+            last_price_response: old.last_price_response,
+            paused: PauseFlags::default(),
+        }
+    }
+}
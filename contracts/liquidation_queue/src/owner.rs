@@ -5,7 +5,6 @@ impl Contract {
     #[payable]
     pub fn update_config(
         &mut self,
-        owner: Option<ValidAccountId>,
         stable_coin_contract: Option<ValidAccountId>,
         requester_contract: Option<ValidAccountId>,
         oracle_payment_token: Option<ValidAccountId>,
@@ -22,10 +21,6 @@ impl Contract {
         assert_one_yocto();
         self.internal_update_price_response();
 
-        if let Some(owner) = owner {
-            self.config.owner = owner.into();
-        }
-
         if let Some(stable_coin_contract) = stable_coin_contract {
             self.config.stable_coin_contract = stable_coin_contract.into();
         }
@@ -71,5 +66,7 @@ impl Contract {
         if let Some(collateral_info) = collateral_info {
             self.config.collateral_info = collateral_info;
         }
+
+        Event::UpdateConfig(&self.config).emit();
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,44 @@
+use crate::*;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum Operation {
+    Bid,
+    Liquidate,
+}
+
+/// Operations halted by the guardian, `true` means paused
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq,
+)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PauseFlags {
+    pub bid: bool,
+    pub liquidate: bool,
+}
+
+impl PauseFlags {
+    pub(crate) fn get(&self, operation: Operation) -> bool {
+        match operation {
+            Operation::Bid => self.bid,
+            Operation::Liquidate => self.liquidate,
+        }
+    }
+
+    pub(crate) fn get_mut(&mut self, operation: Operation) -> &mut bool {
+        match operation {
+            Operation::Bid => &mut self.bid,
+            Operation::Liquidate => &mut self.liquidate,
+        }
+    }
+}
+
+impl Contract {
+    pub(crate) fn pause_flags(&self) -> &PauseFlags {
+        &self.paused
+    }
+
+    pub(crate) fn pause_flags_mut(&mut self) -> &mut PauseFlags {
+        &mut self.paused
+    }
+}
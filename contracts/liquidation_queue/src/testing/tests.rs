@@ -0,0 +1,213 @@
+use near_sdk::test_utils::{accounts, get_logs, VMContextBuilder};
+use near_sdk::{testing_env, MockedBlockchain, RuntimeFeesConfig, VMConfig};
+use std::convert::TryFrom;
+
+use crate::token_receiver::FungibleTokenReceiver;
+use crate::*;
+
+fn setup_contract() -> (VMContextBuilder, Contract) {
+    let mut context = VMContextBuilder::new();
+    testing_env!(context
+        .predecessor_account_id(ValidAccountId::try_from("owner").unwrap())
+        .attached_deposit(1)
+        .build());
+    let contract = Contract::new(
+        ValidAccountId::try_from("owner").unwrap(),
+        ValidAccountId::try_from("usdt").unwrap(),
+        ValidAccountId::try_from("requester").unwrap(),
+        ValidAccountId::try_from("oracle_payment_token").unwrap(),
+        ValidAccountId::try_from("overseer").unwrap(),
+        ValidAccountId::try_from("custody_bnear").unwrap(),
+        D128::new_exp(8, -1),
+        D128::new_exp(1, -2),
+        D128::new_exp(1, -2),
+        0,
+        U64(600),
+        CollateralInfo {
+            bnear_contract: AccountId::from("bnear"),
+            bid_threshold: U128(1_000_000),
+            max_slot: 10,
+            premium_rate_per_slot: D128::new_exp(1, -2),
+        },
+    );
+    (context, contract)
+}
+
+fn submit_bid(context: &mut VMContextBuilder, contract: &mut Contract, amount: u128) {
+    testing_env!(context
+        .predecessor_account_id(ValidAccountId::try_from("usdt").unwrap())
+        .build());
+    contract.ft_on_transfer(
+        AccountId::from("bidder"),
+        U128(amount),
+        String::from("{\"premium_slot\": 0}"),
+    );
+}
+
+fn execute_liquidation(
+    context: &mut VMContextBuilder,
+    contract: &mut Contract,
+    loan: &str,
+) -> PromiseOrValue<U128> {
+    testing_env!(
+        context
+            .predecessor_account_id(accounts(0))
+            .current_account_id(accounts(0))
+            .build(),
+        VMConfig::default(),
+        RuntimeFeesConfig::default(),
+        Default::default(),
+        vec![PromiseResult::Successful(loan.as_bytes().to_vec())]
+    );
+    contract.callback_query_loan_amount(
+        AccountId::from("liquidator"),
+        AccountId::from("borrower"),
+        AccountId::from("market"),
+        AccountId::from("fee_collector"),
+        U128(1_000),
+    )
+}
+
+#[test]
+fn submit_bid_event() {
+    let (mut context, mut contract) = setup_contract();
+    submit_bid(&mut context, &mut contract, 1_000);
+
+    // the bid is active below the bid threshold
+    assert_eq!(U128(1_000), contract.get_state().total_bids);
+    assert!(get_logs()[0].starts_with(
+        "EVENT_JSON:{\"standard\":\"money_market\",\"version\":\"1.0.0\",\"event\":\"submit_bid\",\"data\":{\"idx\":\"1\",\"premium_slot\":0,\"bidder\":\"bidder\",\"amount\":\"1000\""
+    ));
+}
+
+#[test]
+#[should_panic(expected = "Bid is paused")]
+fn bid_while_paused() {
+    let (mut context, mut contract) = setup_contract();
+
+    contract.pause(Operation::Bid);
+    assert_eq!(
+        get_logs(),
+        vec!["EVENT_JSON:{\"standard\":\"money_market\",\"version\":\"1.0.0\",\"event\":\"pause\",\"data\":{\"operation\":\"bid\"}}"]
+    );
+
+    submit_bid(&mut context, &mut contract, 1_000);
+}
+
+#[test]
+#[should_panic(expected = "Liquidate is paused")]
+fn liquidate_while_paused() {
+    let (mut context, mut contract) = setup_contract();
+    contract.pause(Operation::Liquidate);
+
+    testing_env!(context
+        .predecessor_account_id(ValidAccountId::try_from("bnear").unwrap())
+        .build());
+    contract.ft_on_transfer(
+        AccountId::from("custody_bnear"),
+        U128(1_000),
+        String::from("{\"liquidator\": \"liquidator\", \"borrower\": \"borrower\"}"),
+    );
+}
+
+#[test]
+#[should_panic(expected = "This method can only be called by owner1")]
+fn proper_ownership_transfer() {
+    let (mut context, mut contract) = setup_contract();
+    contract.propose_owner(ValidAccountId::try_from("owner1").unwrap());
+
+    testing_env!(context
+        .predecessor_account_id(ValidAccountId::try_from("owner1").unwrap())
+        .build());
+    contract.accept_ownership();
+    assert_eq!(AccountId::from("owner1"), contract.get_config().owner_id);
+    assert_eq!(None, contract.get_config().pending_owner_id);
+
+    // the former owner stays the guardian, but can't unpause
+    testing_env!(context
+        .predecessor_account_id(ValidAccountId::try_from("owner").unwrap())
+        .build());
+    contract.pause(Operation::Bid);
+    contract.unpause(Operation::Bid);
+}
+
+#[test]
+fn liquidation_capped_at_loan() {
+    let (mut context, mut contract) = setup_contract();
+    submit_bid(&mut context, &mut contract, 1_000);
+
+    // 500 repaid with 2% of fees on top, at a price of 1 without premium
+    match execute_liquidation(&mut context, &mut contract, "\"500\"") {
+        PromiseOrValue::Promise(_) => {}
+        PromiseOrValue::Value(_) => panic!("expected a promise"),
+    }
+    assert_eq!(U128(490), contract.get_state().total_bids);
+    assert_eq!(
+        get_logs(),
+        vec![
+            "product: 490000000",
+            "EVENT_JSON:{\"standard\":\"money_market\",\"version\":\"1.0.0\",\"event\":\"execute_liquidation\",\"data\":{\"liquidator\":\"liquidator\",\"collateral_amount\":\"510\",\"repay_amount\":\"500\",\"bid_fee\":\"5\",\"liquidator_fee\":\"5\"}}",
+        ]
+    );
+}
+
+#[test]
+fn liquidation_without_loan() {
+    let (mut context, mut contract) = setup_contract();
+    submit_bid(&mut context, &mut contract, 1_000);
+
+    // nothing to repay, the collateral goes back to the custody
+    match execute_liquidation(&mut context, &mut contract, "\"0\"") {
+        PromiseOrValue::Value(unused) => assert_eq!(U128(1_000), unused),
+        PromiseOrValue::Promise(_) => panic!("expected a value"),
+    }
+    assert_eq!(U128(1_000), contract.get_state().total_bids);
+    assert_eq!(get_logs(), vec!["No loan of borrower to repay in market"]);
+}
+
+#[test]
+#[should_panic(expected = "Not enough bids to execute this liquidation")]
+fn liquidation_without_enough_bids() {
+    let (mut context, mut contract) = setup_contract();
+    submit_bid(&mut context, &mut contract, 100);
+
+    execute_liquidation(&mut context, &mut contract, "\"500\"");
+}
+
+#[test]
+fn failed_repayment_refunds_borrower() {
+    let (mut context, contract) = setup_contract();
+
+    // the bids keep the collateral they bought, the unused rest goes back
+    testing_env!(
+        context
+            .predecessor_account_id(accounts(0))
+            .current_account_id(accounts(0))
+            .build(),
+        VMConfig::default(),
+        RuntimeFeesConfig::default(),
+        Default::default(),
+        vec![PromiseResult::Failed]
+    );
+    assert_eq!(
+        U128(490),
+        contract.callback_execute_liquidation(AccountId::from("borrower"), U128(499), U128(490))
+    );
+    assert_eq!(
+        get_logs(),
+        vec!["Refund 499 of the liquidation proceeds to borrower"]
+    );
+
+    testing_env!(
+        context.build(),
+        VMConfig::default(),
+        RuntimeFeesConfig::default(),
+        Default::default(),
+        vec![PromiseResult::Successful(b"\"400\"".to_vec())]
+    );
+    contract.callback_execute_liquidation(AccountId::from("borrower"), U128(499), U128(490));
+    assert_eq!(
+        get_logs(),
+        vec!["Refund 99 of the liquidation proceeds to borrower"]
+    );
+}
@@ -4,6 +4,7 @@ use crate::*;
 #[serde(crate = "near_sdk::serde")]
 pub struct BnearReceiverPayload {
     pub liquidator: AccountId,
+    pub borrower: AccountId,
     pub repay_address: Option<AccountId>,
     pub fee_address: Option<AccountId>
 }
@@ -38,9 +39,17 @@ impl FungibleTokenReceiver for Contract {
             let repay_address: AccountId = payload.repay_address.unwrap_or(sender_id.clone());
             let fee_address: AccountId = payload.fee_address.unwrap_or(sender_id.clone());
             
-            self.on_receive_execute_liquidation(sender_id, payload.liquidator, repay_address, fee_address, amount);
-
-            return PromiseOrValue::Value(U128(0));
+            // the collateral transfer resolves once the loan is repaid
+            return self
+                .on_receive_execute_liquidation(
+                    sender_id,
+                    payload.liquidator,
+                    payload.borrower,
+                    repay_address,
+                    fee_address,
+                    amount,
+                )
+                .into();
         } else if env::predecessor_account_id() == self.config.stable_coin_contract {
             let payload: StableReceiverPayload =
                 serde_json::from_str(&msg).expect("Failed to parse the payload, invalid `msg` format");
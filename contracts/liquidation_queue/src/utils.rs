@@ -27,9 +27,28 @@ pub trait RequesterContract {
     fn get_data_request(&self, request_id: ValidAccountId) -> Option<DataRequestDetails>;
 }
 
+#[ext_contract(ext_market)]
+pub trait MarketContract {
+    fn get_loan_amount(&self, borrower: AccountId, block_time: Option<u64>) -> U128;
+}
+
 #[ext_contract(ext_self)]
 pub trait Contract {
     fn callback_get_price_response(&mut self, #[callback] result: Option<DataRequestDetails>);
+    fn callback_query_loan_amount(
+        &mut self,
+        liquidator: AccountId,
+        borrower: AccountId,
+        repay_address: AccountId,
+        fee_address: AccountId,
+        amount: U128,
+    ) -> PromiseOrValue<U128>;
+    fn callback_execute_liquidation(
+        &self,
+        borrower: AccountId,
+        repay_amount: U128,
+        unused_collateral: U128,
+    ) -> U128;
 }
 
 pub fn fungible_token_transfer(
@@ -68,6 +87,69 @@ pub fn fungible_token_transfer_call(
 
 #[near_bindgen]
 impl Contract {
+    /// Liquidates the collateral against the loan of borrower, a failed query
+    /// liquidates nothing and the collateral transfer refunds the collateral.
+    #[private]
+    pub fn callback_query_loan_amount(
+        &mut self,
+        liquidator: AccountId,
+        borrower: AccountId,
+        repay_address: AccountId,
+        fee_address: AccountId,
+        amount: U128,
+    ) -> PromiseOrValue<U128> {
+        let loan_amount: u128 = match env::promise_result(0) {
+            PromiseResult::Successful(result) => {
+                serde_json::from_slice::<U128>(&result).map_or(0, |loan| loan.0)
+            }
+            _ => 0,
+        };
+
+        self.internal_execute_liquidation(
+            liquidator,
+            borrower,
+            repay_address,
+            fee_address,
+            amount,
+            loan_amount,
+        )
+    }
+
+    /// ft_transfer_call resolves to the amount the market used to repay the loan,
+    /// the rest is refunded here and sent on to the borrower. The bids keep the
+    /// collateral they bought even if the market used nothing, as they paid for it.
+    #[private]
+    pub fn callback_execute_liquidation(
+        &self,
+        borrower: AccountId,
+        repay_amount: U128,
+        unused_collateral: U128,
+    ) -> U128 {
+        let used_amount: u128 = match env::promise_result(0) {
+            PromiseResult::Successful(result) => {
+                serde_json::from_slice::<U128>(&result).map_or(0, |used| used.0)
+            }
+            _ => 0,
+        };
+
+        if used_amount < repay_amount.0 {
+            env::log(
+                format!(
+                    "Refund {} of the liquidation proceeds to {}",
+                    repay_amount.0 - used_amount, borrower
+                ).as_bytes()
+            );
+            fungible_token_transfer(
+                self.config.stable_coin_contract.clone(),
+                borrower,
+                repay_amount.0 - used_amount,
+            );
+        }
+
+        // the collateral the loan didn't need goes back to the custody
+        unused_collateral
+    }
+
     #[private]
     pub fn callback_get_price_response(&mut self, #[callback] result: Option<DataRequestDetails>) {
         let result: DataRequestDetails = result.expect("ERR: There is no response.");
@@ -2,4 +2,16 @@ use crate::*;
 
 #[near_bindgen]
 impl Contract {
-}
\ No newline at end of file
+    pub fn get_config(&self) -> Config {
+        self.config.clone()
+    }
+
+    pub fn get_state(&self) -> State {
+        State {
+            bid_idx: self.bid_idx,
+            total_bids: self.total_bids,
+            last_price_response: self.last_price_response.clone(),
+            paused: self.paused,
+        }
+    }
+}
@@ -10,7 +10,18 @@ near dev-deploy \
             "decimal": 100000000,
         },
         "stable_coin_contract": "stable_coin.testnet",
+        "atoken_metadata": {
+            "spec": "ft-1.0.0",
+            "name": "aUSD",
+            "symbol": "aUSD",
+            "icon": null,
+            "reference": null,
+            "reference_hash": null,
+            "decimals": 6
+        },
         "overseer_contract": "overseer.synchro.testnet",
+        "collector_contract": "collector.synchro.testnet",
+        "distributor_contract": "distributor.synchro.testnet",
         "base_rate": {
             "num": 10000000,
             "decimal": 100000000,
@@ -0,0 +1,83 @@
+use crate::*;
+
+// Guardian and owner operations, the same file is shared by the market, overseer,
+// custody_bnear and liquidation_queue contracts and tested in the market: keep the
+// copies identical, the operations a contract can pause are in its `pause` module
+
+#[near_bindgen]
+impl Contract {
+  /// The guardian or the owner can pause, only the owner can unpause
+  #[payable]
+  pub fn pause(&mut self, operation: Operation) {
+    assert_one_yocto();
+    self.assert_guardian();
+    *self.pause_flags_mut().get_mut(operation) = true;
+
+    Event::Pause { operation }.emit();
+  }
+
+  #[payable]
+  pub fn unpause(&mut self, operation: Operation) {
+    assert_one_yocto();
+    self.assert_owner();
+    *self.pause_flags_mut().get_mut(operation) = false;
+
+    Event::Unpause { operation }.emit();
+  }
+
+  #[payable]
+  pub fn set_guardian(&mut self, guardian_id: ValidAccountId) {
+    assert_one_yocto();
+    self.assert_owner();
+    self.config.guardian_id = guardian_id.into();
+
+    Event::UpdateConfig(&self.config).emit();
+  }
+
+  /// First step of an ownership transfer, `new_owner_id` has to accept it
+  #[payable]
+  pub fn propose_owner(&mut self, new_owner_id: ValidAccountId) {
+    assert_one_yocto();
+    self.assert_owner();
+    self.config.pending_owner_id = Some(new_owner_id.into());
+
+    Event::UpdateConfig(&self.config).emit();
+  }
+
+  #[payable]
+  pub fn accept_ownership(&mut self) {
+    assert_one_yocto();
+    let predecessor_id = env::predecessor_account_id();
+    if self.config.pending_owner_id.as_ref() != Some(&predecessor_id) {
+      env::panic(b"Can only be called by the pending owner");
+    }
+    self.config.owner_id = predecessor_id;
+    self.config.pending_owner_id = None;
+
+    Event::UpdateConfig(&self.config).emit();
+  }
+
+  #[payable]
+  pub fn cancel_owner_proposal(&mut self) {
+    assert_one_yocto();
+    self.assert_owner();
+    self.config.pending_owner_id = None;
+
+    Event::UpdateConfig(&self.config).emit();
+  }
+}
+
+impl Contract {
+  pub(crate) fn assert_guardian(&self) {
+    let predecessor_id = env::predecessor_account_id();
+    if predecessor_id != self.config.guardian_id && predecessor_id != self.config.owner_id {
+      env::panic(b"Can only be called by the guardian or the owner");
+    }
+  }
+
+  pub(crate) fn assert_not_paused(&self, operation: Operation) {
+    if self.pause_flags().get(operation) {
+      env::panic(format!("{:?} is paused", operation).as_bytes());
+    }
+  }
+}
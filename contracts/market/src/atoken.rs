@@ -0,0 +1,205 @@
+use crate::*;
+
+pub const FT_METADATA_SPEC: &str = "ft-1.0.0";
+
+const GAS_FOR_RESOLVE_TRANSFER: Gas = 5_000_000_000_000;
+
+const GAS_FOR_FT_TRANSFER_CALL: Gas = 25_000_000_000_000 + GAS_FOR_RESOLVE_TRANSFER;
+
+/// NEP-148 metadata of the aToken
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FungibleTokenMetadata {
+  pub spec: String,
+  pub name: String,
+  pub symbol: String,
+  pub icon: Option<String>,
+  pub reference: Option<String>,
+  pub reference_hash: Option<Base64VecU8>,
+  pub decimals: u8,
+}
+
+#[ext_contract(ext_ft_receiver)]
+pub trait ATokenReceiver {
+  fn ft_on_transfer(
+    &mut self,
+    sender_id: AccountId,
+    amount: U128,
+    msg: String,
+  ) -> PromiseOrValue<U128>;
+}
+
+#[ext_contract(ext_ft_resolver)]
+pub trait ATokenResolver {
+  fn ft_resolve_transfer(
+    &mut self,
+    sender_id: AccountId,
+    receiver_id: AccountId,
+    amount: U128,
+  ) -> U128;
+}
+
+/// NEP-141 interest-bearing deposit token, minted on deposit and burned on redeem.
+/// Holders have to be registered with `storage_deposit`.
+#[near_bindgen]
+impl Contract {
+  #[payable]
+  pub fn ft_transfer(&mut self, receiver_id: ValidAccountId, amount: U128, memo: Option<String>) {
+    assert_one_yocto();
+    let sender_id = env::predecessor_account_id();
+    self.internal_atoken_transfer(&sender_id, receiver_id.as_ref(), amount.0, memo);
+  }
+
+  #[payable]
+  pub fn ft_transfer_call(
+    &mut self,
+    receiver_id: ValidAccountId,
+    amount: U128,
+    memo: Option<String>,
+    msg: String,
+  ) -> PromiseOrValue<U128> {
+    assert_one_yocto();
+    let sender_id = env::predecessor_account_id();
+    self.internal_atoken_transfer(&sender_id, receiver_id.as_ref(), amount.0, memo);
+
+    ext_ft_receiver::ft_on_transfer(
+      sender_id.clone(),
+      amount,
+      msg,
+      receiver_id.as_ref(),
+      NO_DEPOSIT,
+      env::prepaid_gas() - GAS_FOR_FT_TRANSFER_CALL,
+    )
+    .then(ext_ft_resolver::ft_resolve_transfer(
+      sender_id,
+      receiver_id.into(),
+      amount,
+      &env::current_account_id(),
+      NO_DEPOSIT,
+      GAS_FOR_RESOLVE_TRANSFER,
+    ))
+    .into()
+  }
+
+  pub fn ft_total_supply(&self) -> U128 {
+    self.state.atoken_total_supply.into()
+  }
+
+  pub fn ft_balance_of(&self, account_id: ValidAccountId) -> U128 {
+    self
+      .collection
+      .atoken_balance_map
+      .get(account_id.as_ref())
+      .unwrap_or(0)
+      .into()
+  }
+
+  pub fn ft_metadata(&self) -> FungibleTokenMetadata {
+    self.atoken_metadata.clone()
+  }
+
+  /// Returns the amount the receiver kept, the unused part goes back to the sender
+  #[private]
+  pub fn ft_resolve_transfer(
+    &mut self,
+    sender_id: AccountId,
+    receiver_id: AccountId,
+    amount: U128,
+  ) -> U128 {
+    assert_eq!(env::promise_results_count(), 1, "This is a callback method");
+
+    let amount: Balance = amount.0;
+    let unused_amount: Balance = match env::promise_result(0) {
+      PromiseResult::NotReady => unreachable!(),
+      PromiseResult::Successful(value) => match near_sdk::serde_json::from_slice::<U128>(&value) {
+        Ok(unused_amount) => std::cmp::min(amount, unused_amount.0),
+        Err(_) => amount,
+      },
+      PromiseResult::Failed => amount,
+    };
+
+    if unused_amount > 0 {
+      let receiver_balance: Balance = self
+        .collection
+        .atoken_balance_map
+        .get(&receiver_id)
+        .unwrap_or(0);
+      let refund_amount: Balance = std::cmp::min(receiver_balance, unused_amount);
+
+      if refund_amount > 0 {
+        self.internal_atoken_withdraw(&receiver_id, refund_amount);
+        // the sender may have unregistered in the meantime, the refund is burned then
+        if self.collection.atoken_balance_map.get(&sender_id).is_some() {
+          self.internal_atoken_deposit(&sender_id, refund_amount);
+        } else {
+          self.state.atoken_total_supply -= refund_amount;
+        }
+      }
+
+      return U128(amount - refund_amount);
+    }
+
+    U128(amount)
+  }
+}
+
+impl Contract {
+  pub(crate) fn internal_atoken_balance_of(&self, account_id: &AccountId) -> Balance {
+    match self.collection.atoken_balance_map.get(account_id) {
+      Some(balance) => balance,
+      None => env::panic(format!("The account {} is not registered", account_id).as_bytes()),
+    }
+  }
+
+  pub(crate) fn internal_mint(&mut self, account_id: &AccountId, amount: Balance) {
+    self.internal_atoken_deposit(account_id, amount);
+    self.state.atoken_total_supply += amount;
+  }
+
+  pub(crate) fn internal_burn(&mut self, account_id: &AccountId, amount: Balance) {
+    self.internal_atoken_withdraw(account_id, amount);
+    self.state.atoken_total_supply -= amount;
+  }
+
+  pub(crate) fn internal_atoken_transfer(
+    &mut self,
+    sender_id: &AccountId,
+    receiver_id: &AccountId,
+    amount: Balance,
+    memo: Option<String>,
+  ) {
+    if sender_id == receiver_id {
+      env::panic(b"Sender and receiver should be different");
+    }
+    if amount == 0 {
+      env::panic(b"The amount should be a positive number");
+    }
+
+    self.internal_atoken_withdraw(sender_id, amount);
+    self.internal_atoken_deposit(receiver_id, amount);
+
+    env::log(format!("Transfer {} from {} to {}", amount, sender_id, receiver_id).as_bytes());
+    if let Some(memo) = memo {
+      env::log(format!("Memo: {}", memo).as_bytes());
+    }
+  }
+
+  fn internal_atoken_deposit(&mut self, account_id: &AccountId, amount: Balance) {
+    let balance: Balance = self.internal_atoken_balance_of(account_id);
+    self
+      .collection
+      .atoken_balance_map
+      .insert(account_id, &(balance + amount));
+  }
+
+  fn internal_atoken_withdraw(&mut self, account_id: &AccountId, amount: Balance) {
+    let balance: Balance = self.internal_atoken_balance_of(account_id);
+    if balance < amount {
+      env::panic(b"The account doesn't have enough balance");
+    }
+    self
+      .collection
+      .atoken_balance_map
+      .insert(account_id, &(balance - amount));
+  }
+}
@@ -2,69 +2,85 @@ use crate::*;
 
 #[near_bindgen]
 impl Contract {
-  pub fn borrow_stable(&mut self, borrow_amount: Balance) {
-    let borrower = env::predecessor_account_id();
-
-    let mut liability: BorrowerInfo = self.get_borrower_info_map(&borrower);
-
-    self.compute_interest(env::block_index(), None);
-    self.compute_borrower_interest(&mut liability);
-
-    self.compute_reward(env::block_index());
-    self.compute_borrower_reward(&mut liability);
+  /// Borrows against the collateral of `on_behalf_of` (the caller by default).
+  /// A delegate needs an allowance from that account and receives the stable coin.
+  pub fn borrow_stable(&mut self, borrow_amount: Balance, on_behalf_of: Option<AccountId>) {
+    self.assert_not_paused(Operation::Borrow);
+    let receiver = env::predecessor_account_id();
+    let borrower = on_behalf_of.unwrap_or_else(|| receiver.clone());
+    if borrower != receiver {
+      self.assert_credit_allowance(&borrower, &receiver, borrow_amount);
+    }
 
-    ext_overseer::get_borrow_limit(
+    // the limit is net of the loans in the other markets of the overseer
+    ext_overseer::query_borrow_limit(
       borrower,
-      Some(env::block_timestamp()),
       &self.config.overseer_contract,
       NO_DEPOSIT,
       SINGLE_CALL_GAS,
     )
     .then(ext_self::callback_borrow_stable(
       borrow_amount,
-      liability,
+      receiver,
       &env::current_account_id(),
       NO_DEPOSIT,
       SINGLE_CALL_GAS,
     ));
   }
 
-  pub fn repay_stable_from_liquidation(&mut self, borrower: AccountId, prev_balance: Balance) {
-    self.assert_overseer();
+  /// Overwrites the internal stable coin ledger with the actual balance of the market,
+  /// closing a flash loan that was never repaid
+  pub fn reconcile_stable_balance(&mut self) {
+    self.assert_owner();
 
-    let cur_balance: Balance = env::account_balance();
-    let balance_diff: u128 = cur_balance - prev_balance;
-    self.repay_stable(borrower, balance_diff);
+    fungible_token::ft_balance_of(
+      env::current_account_id(),
+      &self.config.stable_coin_contract,
+      NO_DEPOSIT,
+      SINGLE_CALL_GAS,
+    )
+    .then(ext_self::callback_reconcile_stable_balance(
+      &env::current_account_id(),
+      NO_DEPOSIT,
+      SINGLE_CALL_GAS,
+    ));
   }
 
-  pub fn repay_stable(&mut self, borrower: AccountId, amount: Balance) {
+  /// Returns the amount exceeding the loan, which should be refunded to the payer.
+  /// With `repay_all`, `amount` has to cover the whole debt accrued at execution time.
+  pub(crate) fn repay_stable(
+    &mut self,
+    borrower: AccountId,
+    amount: Balance,
+    repay_all: bool,
+  ) -> Balance {
     if amount == 0 {
-      env::panic("".as_bytes());
+      env::panic("Zero Repay".as_bytes());
     }
 
     let mut liability: BorrowerInfo = self.get_borrower_info_map(&borrower);
 
-    let block_height = env::block_index();
-
-    self.compute_interest(block_height, Some(amount));
-    self.compute_borrower_interest(&mut liability);
+    // accrued on the ledger balance, so the debt is exact at execution time
+    self.state = self.compute_state_at(current_block_time());
+    compute_borrower_interest(&self.state, &mut liability);
+    compute_borrower_reward(&self.state, &mut liability);
+
+    if repay_all && amount < liability.loan_amount {
+      env::panic(
+        format!(
+          "Repay amount {} is less than the debt {}",
+          amount, liability.loan_amount
+        )
+        .as_bytes(),
+      );
+    }
 
-    self.compute_reward(block_height);
-    self.compute_borrower_reward(&mut liability);
+    self.state.stable_balance += amount;
 
     let repay_amount: Balance;
     if liability.loan_amount < amount {
       repay_amount = liability.loan_amount;
       liability.loan_amount = 0;
-
-      fungible_token::ft_transfer(
-        borrower.clone(),
-        U128::from(amount - repay_amount),
-        None,
-        &self.config.stable_coin_contract,
-        1,
-        SINGLE_CALL_GAS,
-      );
     } else {
       repay_amount = amount;
       liability.loan_amount = liability.loan_amount - repay_amount;
@@ -73,127 +89,171 @@ impl Contract {
     self.state.total_liabilities = self.state.total_liabilities - repay_amount;
 
     self.add_borrower_info_map(&borrower, &liability);
+
+    Event::RepayStable {
+      borrower: &borrower,
+      repay_amount: U128::from(repay_amount),
+    }
+    .emit();
+
+    // refunded to the payer
+    let refund_amount: Balance = amount - repay_amount;
+    self.state.stable_balance -= refund_amount;
+
+    refund_amount
   }
 
   pub fn claim_reward(&mut self) {
     let borrower = env::predecessor_account_id();
     let mut liability: BorrowerInfo = self.get_borrower_info_map(&borrower);
 
-    let block_height = env::block_index();
+    let block_time = current_block_time();
 
-    self.compute_interest(block_height, None);
-    self.compute_borrower_interest(&mut liability);
+    self.compute_interest(block_time);
+    compute_borrower_interest(&self.state, &mut liability);
 
-    self.compute_reward(block_height);
-    self.compute_borrower_reward(&mut liability);
+    compute_reward(&mut self.state, block_time);
+    compute_borrower_reward(&self.state, &mut liability);
 
     let claim_amount: Balance = liability.pending_rewards.as_u128();
-    liability.pending_rewards = liability.pending_rewards - claim_amount; // TODO why not assign 0 ?
+    if claim_amount == 0 {
+      env::panic("No rewards to claim".as_bytes());
+    }
 
+    // deducted before the spend, so a second claim can't pay the same rewards,
+    // the callback gives them back if the distributor fails
+    liability.pending_rewards = liability.pending_rewards - claim_amount;
     self.add_borrower_info_map(&borrower, &liability);
 
-    // TODO distributor contract should send reward to borrwer
-  }
-
-  pub(crate) fn compute_interest(
-    &self,
-    block_height: BlockHeight,
-    deposit_amount: Option<Balance>,
-  ) {
-    if self.state.last_interest_updated >= block_height {
-      return;
-    }
-
-    self.ft_info().then(ext_self::callback_compute_interset(
-      block_height,
-      deposit_amount,
+    ext_distributor::spend(
+      borrower.clone(),
+      U128::from(claim_amount),
+      &self.config.distributor_contract,
+      NO_DEPOSIT,
+      SINGLE_CALL_GAS,
+    )
+    .then(ext_self::callback_claim_reward(
+      borrower,
+      U128::from(claim_amount),
       &env::current_account_id(),
       NO_DEPOSIT,
       SINGLE_CALL_GAS,
     ));
   }
 
-  pub(crate) fn compute_interest_raw(
-    &mut self,
-    block_height: BlockHeight,
-    stable_coin_total_supply: u128,
-    borrow_rate: D128,
-    target_deposit_rate: D128,
-  ) {
-    if self.state.last_interest_updated >= block_height {
+  /// Accrues interest on the internal stable coin ledger
+  pub(crate) fn compute_interest(&mut self, block_time: u64) {
+    if self.state.last_interest_updated >= block_time {
       return;
     }
 
-    let passed_blocks: BlockHeight = block_height - self.state.last_interest_updated;
+    let balance: Balance = self.state.stable_balance;
+    let stable_coin_total_supply: Balance = self.state.atoken_total_supply;
+
+    let borrow_rate = self.get_borrow_rate(
+      balance,
+      self.state.total_liabilities,
+      self.state.total_reserves,
+    );
+
+    let target_deposit_rate = self.state.target_deposit_rate;
+
+    compute_interest_raw(
+      &mut self.state,
+      block_time,
+      balance,
+      stable_coin_total_supply,
+      borrow_rate,
+      target_deposit_rate,
+    );
+  }
+
+  pub fn assert_max_borrow_factor(&self, current_balance: Balance, borrow_amount: Balance) {
+    if self.state.total_liabilities + borrow_amount
+      > (current_balance + self.state.total_liabilities - self.state.total_reserves)
+        * self.config.max_borrow_factor
+    {
+      env::panic("Max Borrow Factor Reached".as_bytes());
+    }
 
-    let interest_factor: D128 = borrow_rate * passed_blocks as u128;
-    let interest_accrued: D128 = self.state.total_liabilities * interest_factor;
+    if borrow_amount + self.state.total_reserves > D128::new_exp(current_balance, 0) {
+      env::panic("No Stable Available".as_bytes());
+    }
+  }
 
-    self.state.global_interest_index =
-      (D128::one() + interest_factor) * self.state.global_interest_index;
-    self.state.total_liabilities = interest_accrued * self.state.total_liabilities;
+  pub fn assert_borrow_cap(&self, liability: &BorrowerInfo, borrow_amount: Balance) {
+    if let Some(global_borrow_cap) = self.config.global_borrow_cap {
+      if self.state.total_liabilities + borrow_amount > D128::new_exp(global_borrow_cap, 0) {
+        env::panic("Global Borrow Cap Reached".as_bytes());
+      }
+    }
 
-    let balance: Balance = env::account_balance();
+    if let Some(account_borrow_cap) = self.config.account_borrow_cap {
+      if liability.loan_amount + borrow_amount > account_borrow_cap {
+        env::panic("Account Borrow Cap Reached".as_bytes());
+      }
+    }
+  }
+}
 
-    let mut exchange_rate: D128 = self.compute_exchange_rate_raw(stable_coin_total_supply, balance);
-    let effective_deposit_rate: D128 = exchange_rate / self.state.prev_exchange_rate;
-    let deposit_rate: D128 = (effective_deposit_rate - D128::one()) / passed_blocks as u128;
+pub(crate) fn compute_interest_raw(
+  state: &mut State,
+  block_time: u64,
+  balance: Balance,
+  stable_coin_total_supply: u128,
+  borrow_rate: D128,
+  target_deposit_rate: D128,
+) {
+  if state.last_interest_updated >= block_time {
+    return;
+  }
 
-    if deposit_rate > target_deposit_rate {
-      let excess_deposit_rate: D128 = deposit_rate - target_deposit_rate;
-      let prev_deposit: u128 =
-        (self.state.prev_exchange_rate).mul_int(self.state.prev_stable_coin_total_supply);
+  let passed_time: u64 = block_time - state.last_interest_updated;
 
-      let excess_yield: u128 = excess_deposit_rate.mul_int(prev_deposit * passed_blocks as u128);
+  let interest_accrued: D128 = borrow_rate.interest_over(state.total_liabilities, passed_time);
 
-      self.state.total_reserves = excess_yield + self.state.total_reserves;
-      exchange_rate = self.compute_exchange_rate_raw(stable_coin_total_supply, balance);
-    }
+  state.global_interest_index = state.global_interest_index.accrue(borrow_rate, passed_time);
+  state.total_liabilities = interest_accrued + state.total_liabilities;
 
-    self.state.prev_stable_coin_total_supply = stable_coin_total_supply;
-    self.state.prev_exchange_rate = exchange_rate;
-    self.state.last_interest_updated = block_height;
-  }
+  // the depositors earn up to the target deposit rate, the excess goes to the reserves
+  let prev_deposit: u128 = (state.prev_exchange_rate).mul_int(stable_coin_total_supply);
+  let target_interest: D128 =
+    target_deposit_rate.interest_over(D128::new_exp(prev_deposit, 0), passed_time);
 
-  pub fn compute_borrower_interest(&self, liability: &mut BorrowerInfo) {
-    liability.loan_amount = (liability.loan_amount * self.state.global_interest_index
-      / liability.interest_index)
-      .as_u128();
-    liability.interest_index = self.state.global_interest_index;
+  if interest_accrued > target_interest {
+    state.total_reserves = state.total_reserves + (interest_accrued - target_interest);
   }
 
-  pub fn compute_reward(&mut self, block_height: BlockHeight) {
-    if self.state.last_reward_updated >= block_height {
-      return;
-    }
+  state.prev_exchange_rate = compute_exchange_rate_raw(state, stable_coin_total_supply, balance);
+  state.last_interest_updated = block_time;
+}
 
-    let passed_blocks: BlockHeight = block_height - self.state.last_reward_updated;
-    let reward_accrued: u128 = self.state.anc_emission_rate.mul_int(passed_blocks.into());
-    let borrow_amount = self.state.total_liabilities / self.state.global_interest_index;
+pub(crate) fn compute_borrower_interest(state: &State, liability: &mut BorrowerInfo) {
+  liability.loan_amount = state
+    .global_interest_index
+    .grow(liability.loan_amount, liability.interest_index);
+  liability.interest_index = state.global_interest_index;
+}
 
-    if reward_accrued != 0 && borrow_amount != D128::zero() {
-      self.state.global_reward_index =
-        self.state.global_reward_index + reward_accrued / borrow_amount;
-    }
+pub(crate) fn compute_reward(state: &mut State, block_time: u64) {
+  if state.last_reward_updated >= block_time {
+    return;
   }
 
-  pub(crate) fn compute_borrower_reward(&self, liability: &mut BorrowerInfo) {
-    liability.pending_rewards = liability.pending_rewards
-      + liability.loan_amount / self.state.global_interest_index
-        * (self.state.global_reward_index - liability.reward_index);
-    liability.reward_index = self.state.global_reward_index;
+  let passed_time: u64 = block_time - state.last_reward_updated;
+  let reward_accrued: u128 = state.anc_emission_rate.mul_int(passed_time.into());
+  let borrow_amount = state.total_liabilities / state.global_interest_index;
+
+  if reward_accrued != 0 && borrow_amount != D128::zero() {
+    state.global_reward_index = state.global_reward_index + reward_accrued / borrow_amount;
   }
 
-  pub fn assert_max_borrow_factor(&self, current_balance: Balance, borrow_amount: Balance) {
-    if self.state.total_liabilities + borrow_amount
-      > (current_balance + self.state.total_liabilities - self.state.total_reserves)
-        * self.config.max_borrow_factor
-    {
-      env::panic("Max Borrow Factor Reached".as_bytes());
-    }
+  state.last_reward_updated = block_time;
+}
 
-    if borrow_amount + self.state.total_reserves - current_balance > D128::zero() {
-      env::panic("No Stable Available".as_bytes());
-    }
-  }
+pub(crate) fn compute_borrower_reward(state: &State, liability: &mut BorrowerInfo) {
+  liability.pending_rewards = liability.pending_rewards
+    + liability.loan_amount / state.global_interest_index
+      * (state.global_reward_index - liability.reward_index);
+  liability.reward_index = state.global_reward_index;
 }
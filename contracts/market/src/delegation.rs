@@ -0,0 +1,121 @@
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+  /// Lets `delegate_id` borrow up to `allowance` against the caller's collateral,
+  /// the debt is recorded on the caller. Overwrites any previous allowance.
+  /// The storage of a new approval is paid with the attached deposit.
+  #[payable]
+  pub fn approve_delegate(&mut self, delegate_id: ValidAccountId, allowance: U128) {
+    if env::attached_deposit() == 0 {
+      env::panic(b"Requires attached deposit of at least 1 yoctoNEAR");
+    }
+    let initial_storage_usage = env::storage_usage();
+    let owner_id = env::predecessor_account_id();
+    let delegate_id: AccountId = delegate_id.into();
+
+    if owner_id == delegate_id {
+      env::panic(b"Can't delegate credit to yourself");
+    }
+    // only registered borrowers can delegate
+    self.get_borrower_info_map(&owner_id);
+
+    self
+      .collection
+      .credit_allowance_map
+      .insert(&(owner_id.clone(), delegate_id.clone()), &allowance.0);
+    self.internal_settle_storage(initial_storage_usage);
+
+    Event::ApproveDelegate {
+      owner_id: &owner_id,
+      delegate_id: &delegate_id,
+      allowance,
+    }
+    .emit();
+  }
+
+  /// Removes the approval and refunds its storage
+  #[payable]
+  pub fn revoke_delegate(&mut self, delegate_id: ValidAccountId) {
+    assert_one_yocto();
+    let initial_storage_usage = env::storage_usage();
+    let owner_id = env::predecessor_account_id();
+    let delegate_id: AccountId = delegate_id.into();
+
+    self
+      .collection
+      .credit_allowance_map
+      .remove(&(owner_id.clone(), delegate_id.clone()));
+    self.internal_settle_storage(initial_storage_usage);
+
+    Event::RevokeDelegate {
+      owner_id: &owner_id,
+      delegate_id: &delegate_id,
+    }
+    .emit();
+  }
+
+  pub fn get_credit_allowance(&self, owner_id: AccountId, delegate_id: AccountId) -> U128 {
+    self
+      .collection
+      .credit_allowance_map
+      .get(&(owner_id, delegate_id))
+      .unwrap_or(0)
+      .into()
+  }
+}
+
+impl Contract {
+  pub(crate) fn assert_credit_allowance(
+    &self,
+    owner_id: &AccountId,
+    delegate_id: &AccountId,
+    amount: Balance,
+  ) {
+    let allowance: Balance = self
+      .collection
+      .credit_allowance_map
+      .get(&(owner_id.clone(), delegate_id.clone()))
+      .unwrap_or(0);
+    if amount > allowance {
+      env::panic(
+        format!(
+          "Borrow amount exceeds the credit allowance of {}: {}",
+          delegate_id, allowance
+        )
+        .as_bytes(),
+      );
+    }
+  }
+
+  pub(crate) fn internal_use_credit_allowance(
+    &mut self,
+    owner_id: &AccountId,
+    delegate_id: &AccountId,
+    amount: Balance,
+  ) {
+    self.assert_credit_allowance(owner_id, delegate_id, amount);
+    let key = (owner_id.clone(), delegate_id.clone());
+    let allowance: Balance = self.collection.credit_allowance_map.get(&key).unwrap();
+    self
+      .collection
+      .credit_allowance_map
+      .insert(&key, &(allowance - amount));
+  }
+
+  /// Gives back the allowance of a failed borrow, unless it was revoked in the meantime
+  pub(crate) fn internal_restore_credit_allowance(
+    &mut self,
+    owner_id: &AccountId,
+    delegate_id: &AccountId,
+    amount: Balance,
+  ) {
+    let key = (owner_id.clone(), delegate_id.clone());
+    if let Some(allowance) = self.collection.credit_allowance_map.get(&key) {
+      self
+        .collection
+        .credit_allowance_map
+        .insert(&key, &(allowance + amount));
+    }
+  }
+}
@@ -1,80 +1,102 @@
 use crate::*;
 
 impl Contract {
-  pub fn deposit_stable(&mut self, deposit_amount: Balance) {
+  // Executor: stable coin contract, on behalf of depositor
+  pub(crate) fn deposit_stable(&mut self, depositor: AccountId, deposit_amount: Balance) {
+    self.assert_not_paused(Operation::Deposit);
     if deposit_amount == 0 {
       env::panic("Zero Deposit".as_bytes());
     }
+    // panics while the stable coin can still be refunded by ft_resolve_transfer
+    self.internal_atoken_balance_of(&depositor);
 
-    let depositor = env::predecessor_account_id();
+    self.compute_interest(current_block_time());
+    compute_reward(&mut self.state, current_block_time());
 
-    let block_height = env::block_index();
+    // the deposit is credited after accrual, so it doesn't skew the exchange rate
+    let exchange_rate: D128 = self.compute_exchange_rate();
+    self.state.stable_balance += deposit_amount;
 
-    self.compute_interest(block_height, Some(deposit_amount));
-    self.compute_reward(block_height);
+    let mint_amount: Balance = (deposit_amount / exchange_rate).as_u128();
+    self.internal_mint(&depositor, mint_amount);
 
-    self
-      .compute_exchange_rate(Some(deposit_amount))
-      .then(ext_self::callback_deposit_stable(
-        deposit_amount,
-        depositor,
-        &env::current_account_id(),
-        NO_DEPOSIT,
-        SINGLE_CALL_GAS,
-      ));
+    Event::DepositStable {
+      depositor: &depositor,
+      deposit_amount: U128::from(deposit_amount),
+      mint_amount: U128::from(mint_amount),
+    }
+    .emit();
   }
 
-  pub fn redeem_stable(&mut self, burn_amount: Balance) {
-    let block_height = env::block_index();
+  pub fn is_redeemable(&self, current_balance: Balance, redeem_amount: D128) -> bool {
+    redeem_amount + self.state.total_reserves <= D128::new(current_balance * 100_000_000)
+  }
 
-    self.compute_interest(block_height, None);
-    self.compute_reward(block_height);
+  pub fn compute_exchange_rate(&self) -> D128 {
+    compute_exchange_rate_raw(
+      &self.state,
+      self.state.atoken_total_supply,
+      self.state.stable_balance,
+    )
+  }
+}
+
+#[near_bindgen]
+impl Contract {
+  /// Burns `burn_amount` aToken of the caller for stable coin at the exchange rate
+  #[payable]
+  pub fn redeem_stable(&mut self, burn_amount: U128) {
+    assert_one_yocto();
+    self.assert_not_paused(Operation::Redeem);
+    let burn_amount: Balance = burn_amount.0;
+    if burn_amount == 0 {
+      env::panic("Zero Redeem".as_bytes());
+    }
 
     let redeemer = env::predecessor_account_id();
 
-    self
-      .compute_exchange_rate(None)
-      .then(ext_self::callback_redeem_stable(
-        burn_amount,
-        redeemer,
-        &env::current_account_id(),
-        NO_DEPOSIT,
-        SINGLE_CALL_GAS,
-      ));
-  }
+    self.compute_interest(current_block_time());
+    compute_reward(&mut self.state, current_block_time());
 
-  pub fn assert_redeem_amount(&self, current_balance: Balance, redeem_amount: D128) {
-    if redeem_amount + self.state.total_reserves > D128::new(current_balance * 100_000_000) {
-      env::panic("".as_bytes());
+    let exchange_rate: D128 = self.compute_exchange_rate();
+    let redeem_amount: Balance = (burn_amount * exchange_rate).as_u128();
+
+    // staged here, reverted if the transfer fails
+    self.internal_burn(&redeemer, burn_amount);
+    if !self.is_redeemable(self.state.stable_balance, D128::new_exp(redeem_amount, 0)) {
+      env::panic("No Stable Available".as_bytes());
     }
-  }
+    self.state.stable_balance -= redeem_amount;
 
-  pub fn compute_exchange_rate(&self, deposit_amount: Option<Balance>) -> Promise {
-    fungible_token::ft_total_supply(
+    fungible_token::ft_transfer(
+      redeemer.clone(),
+      U128::from(redeem_amount),
+      None,
       &self.config.stable_coin_contract,
-      NO_DEPOSIT,
-      SINGLE_CALL_GAS,
+      1,
+      TRANSFER_CALL_GAS,
     )
-    .then(ext_self::callback_compute_exchange_rate(
-      env::account_balance(),
-      deposit_amount,
-      &self.config.stable_coin_contract,
+    .then(ext_self::callback_redeem_transfer(
+      redeemer,
+      U128::from(burn_amount),
+      U128::from(redeem_amount),
+      &env::current_account_id(),
       NO_DEPOSIT,
-      SINGLE_CALL_GAS,
-    ))
+      CALLBACK_GAS,
+    ));
   }
+}
 
-  pub fn compute_exchange_rate_raw(
-    &self,
-    stable_coin_total_supply: u128,
-    balance: Balance,
-  ) -> D128 {
-    if stable_coin_total_supply == 0 {
-      return D128::one();
-    }
+pub(crate) fn compute_exchange_rate_raw(
+  state: &State,
+  stable_coin_total_supply: u128,
+  balance: Balance,
+) -> D128 {
+  if stable_coin_total_supply == 0 {
+    return D128::one();
+  }
 
-    // TODO check overflow
+  // TODO check overflow
 
-    (balance * self.state.total_liabilities - self.state.total_reserves) / stable_coin_total_supply
-  }
+  (balance + state.total_liabilities - state.total_reserves) / stable_coin_total_supply
 }
@@ -0,0 +1,83 @@
+use crate::*;
+
+const EVENT_STANDARD: &str = "money_market";
+const EVENT_STANDARD_VERSION: &str = "1.0.0";
+
+/// NEP-297 events of the market, logged as `EVENT_JSON:{..}`
+#[derive(Serialize)]
+#[serde(
+  crate = "near_sdk::serde",
+  tag = "event",
+  content = "data",
+  rename_all = "snake_case"
+)]
+pub(crate) enum Event<'a> {
+  DepositStable {
+    depositor: &'a AccountId,
+    deposit_amount: U128,
+    mint_amount: U128,
+  },
+  RedeemStable {
+    redeemer: &'a AccountId,
+    burn_amount: U128,
+    redeem_amount: U128,
+  },
+  BorrowStable {
+    borrower: &'a AccountId,
+    receiver: &'a AccountId,
+    borrow_amount: U128,
+  },
+  RepayStable {
+    borrower: &'a AccountId,
+    repay_amount: U128,
+  },
+  ClaimReward {
+    borrower: &'a AccountId,
+    claim_amount: U128,
+  },
+  ApproveDelegate {
+    owner_id: &'a AccountId,
+    delegate_id: &'a AccountId,
+    allowance: U128,
+  },
+  RevokeDelegate {
+    owner_id: &'a AccountId,
+    delegate_id: &'a AccountId,
+  },
+  AddFlashLoanReceiver {
+    receiver_id: &'a AccountId,
+  },
+  RemoveFlashLoanReceiver {
+    receiver_id: &'a AccountId,
+  },
+  Pause {
+    operation: Operation,
+  },
+  Unpause {
+    operation: Operation,
+  },
+  UpdateConfig(&'a Config),
+  UpdateInterestModelConfig(&'a InterestModelConfig),
+  UpdateDistributionModelConfig(&'a DistributionModelConfig),
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<'a> {
+  standard: &'static str,
+  version: &'static str,
+  #[serde(flatten)]
+  event: &'a Event<'a>,
+}
+
+impl Event<'_> {
+  pub(crate) fn emit(&self) {
+    let log = EventLog {
+      standard: EVENT_STANDARD,
+      version: EVENT_STANDARD_VERSION,
+      event: self,
+    };
+    let json = near_sdk::serde_json::to_string(&log).unwrap();
+    env::log(format!("EVENT_JSON:{}", json).as_bytes());
+  }
+}
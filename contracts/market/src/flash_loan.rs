@@ -0,0 +1,68 @@
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+  /// Lends `amount` stable coin to `receiver_id` and calls its `on_flash_loan` hook.
+  /// The receiver has to send back principal plus fee with `ft_transfer_call`
+  /// and a `repay_flash_loan` msg, returning that promise from the hook.
+  /// Receipts are not atomic, so only receivers trusted by the owner can borrow.
+  #[payable]
+  pub fn flash_loan(&mut self, receiver_id: AccountId, amount: U128, msg: String) {
+    assert_one_yocto();
+    self.assert_not_paused(Operation::Borrow);
+    if !self.collection.flash_loan_receivers.contains(&receiver_id) {
+      env::panic(format!("The flash loan receiver {} is not trusted", receiver_id).as_bytes());
+    }
+
+    let amount: Balance = amount.0;
+    if amount == 0 {
+      env::panic(b"Zero Flash Loan");
+    }
+    if self.state.flash_loan_due != 0 {
+      env::panic(b"Another flash loan is in progress");
+    }
+    if D128::new_exp(amount, 0) + self.state.total_reserves
+      > D128::new_exp(self.state.stable_balance, 0)
+    {
+      env::panic(b"No Stable Available");
+    }
+
+    let fee: Balance = self.config.flash_loan_fee.mul_int(amount);
+    self.state.stable_balance -= amount;
+    self.state.flash_loan_due = amount + fee;
+    self.state.flash_loan_repaid = 0;
+
+    fungible_token::ft_transfer(
+      receiver_id.clone(),
+      U128(amount),
+      None,
+      &self.config.stable_coin_contract,
+      1,
+      TRANSFER_CALL_GAS,
+    )
+    .then(ext_self::callback_flash_loan_transfer(
+      receiver_id,
+      env::predecessor_account_id(),
+      U128(amount),
+      U128(fee),
+      msg,
+      &env::current_account_id(),
+      NO_DEPOSIT,
+      FLASH_LOAN_CALLBACK_GAS,
+    ));
+  }
+
+  /// Returns the amount exceeding what the flash loan in progress still owes
+  pub(crate) fn repay_flash_loan(&mut self, amount: Balance) -> Balance {
+    if self.state.flash_loan_due == 0 {
+      env::panic(b"No flash loan in progress");
+    }
+
+    let outstanding: Balance = self.state.flash_loan_due - self.state.flash_loan_repaid;
+    let repay_amount: Balance = std::cmp::min(amount, outstanding);
+    self.state.flash_loan_repaid += repay_amount;
+    self.state.stable_balance += repay_amount;
+
+    amount - repay_amount
+  }
+}
@@ -1,5 +1,22 @@
 use crate::*;
 
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum TokenReceiverMsg {
+    /// stable coin: deposit and receive aToken
+    DepositStable,
+    /// stable coin: repay the loan of `borrower` (the sender by default),
+    /// `repay_all` fails unless the whole debt is covered and refunds the excess
+    RepayStable {
+        borrower: Option<AccountId>,
+        repay_all: Option<bool>,
+    },
+    /// stable coin: pay back the flash loan in progress
+    RepayFlashLoan,
+    /// stable coin: interest buffer subsidy from the overseer, not a deposit
+    DistributeInterest,
+}
+
 pub trait FungibleTokenReceiver {
     fn ft_on_transfer(
         &mut self,
@@ -11,19 +28,57 @@ pub trait FungibleTokenReceiver {
 
 #[near_bindgen]
 impl FungibleTokenReceiver for Contract {
+    /// Returns the amount of tokens to refund to `sender_id`
     fn ft_on_transfer(
         &mut self,
         sender_id: AccountId,
         amount: U128,
         msg: String,
     ) -> PromiseOrValue<U128> {
-        if env::predecessor_account_id() == self.config.stable_coin_contract {
-            self.redeem_stable(amount.0);
-            return PromiseOrValue::Value(U128(0));
-        } else {
+        let token = env::predecessor_account_id();
+
+        // aToken is redeemed with `redeem_stable` instead, as it lives in this contract
+        if token != self.config.stable_coin_contract {
             env::log(b"Only whitelisted tokens can transfer_call to this");
 
             return PromiseOrValue::Value(amount);
         }
+
+        let payload: TokenReceiverMsg = near_sdk::serde_json::from_str(&msg)
+            .expect("Failed to parse the payload, invalid `msg` format");
+
+        match payload {
+            TokenReceiverMsg::DepositStable => {
+                self.deposit_stable(sender_id, amount.0);
+
+                PromiseOrValue::Value(U128(0))
+            }
+            TokenReceiverMsg::RepayStable {
+                borrower,
+                repay_all,
+            } => {
+                let borrower: AccountId = borrower.unwrap_or_else(|| sender_id.clone());
+                let refund_amount: Balance =
+                    self.repay_stable(borrower, amount.0, repay_all.unwrap_or(false));
+
+                PromiseOrValue::Value(U128(refund_amount))
+            }
+            TokenReceiverMsg::RepayFlashLoan => {
+                let refund_amount: Balance = self.repay_flash_loan(amount.0);
+
+                PromiseOrValue::Value(U128(refund_amount))
+            }
+            TokenReceiverMsg::DistributeInterest => {
+                if sender_id != self.config.overseer_contract {
+                    env::log(b"Only the overseer can distribute interest");
+
+                    return PromiseOrValue::Value(amount);
+                }
+                // the overseer reports it as distributed interest in the next epoch operation
+                self.state.stable_balance += amount.0;
+
+                PromiseOrValue::Value(U128(0))
+            }
+        }
     }
 }
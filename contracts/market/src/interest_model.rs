@@ -5,6 +5,10 @@ use crate::*;
 pub struct InterestModelConfig {
   pub base_rate: D128,
   pub interest_multiplier: D128,
+  // above this utilization ratio, jump_multiplier is applied instead of interest_multiplier.
+  // one() disables the kink
+  pub optimal_utilization: D128,
+  pub jump_multiplier: D128,
 }
 
 #[near_bindgen]
@@ -15,16 +19,38 @@ impl Contract {
     total_liabilities: D128,
     total_reserves: D128,
   ) -> D128 {
-    let total_value_in_market =
-      D128::new_exp(market_balance, 0) + total_liabilities - total_reserves;
-    let utilization_ratio: D128 = if total_value_in_market == D128::zero() {
-      D128::new(0)
+    let utilization_ratio: D128 =
+      compute_utilization_ratio(market_balance, total_liabilities, total_reserves);
+
+    let config = &self.interest_model_config;
+    let rate = if utilization_ratio > config.optimal_utilization {
+      let excess_utilization: D128 = utilization_ratio - config.optimal_utilization;
+      config.optimal_utilization * config.interest_multiplier
+        + excess_utilization * config.jump_multiplier
+        + config.base_rate
     } else {
-      total_liabilities / total_value_in_market
+      utilization_ratio * config.interest_multiplier + config.base_rate
     };
-
-    let rate = utilization_ratio * self.interest_model_config.interest_multiplier
-      + self.interest_model_config.base_rate;
     rate
   }
+
+  pub(crate) fn assert_optimal_utilization(optimal_utilization: D128) {
+    if optimal_utilization == D128::zero() || optimal_utilization > D128::one() {
+      env::panic("optimal_utilization must be in (0, 1]".as_bytes());
+    }
+  }
+}
+
+pub(crate) fn compute_utilization_ratio(
+  market_balance: Balance,
+  total_liabilities: D128,
+  total_reserves: D128,
+) -> D128 {
+  let total_value_in_market =
+    D128::new_exp(market_balance, 0) + total_liabilities - total_reserves;
+  if total_value_in_market == D128::zero() {
+    D128::new(0)
+  } else {
+    total_liabilities / total_value_in_market
+  }
 }
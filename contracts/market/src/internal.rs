@@ -18,17 +18,41 @@ impl Contract {
     );
   }
 
-  pub fn ft_info(&self) -> Promise {
-    fungible_token::ft_total_supply(
-      &self.config.stable_coin_contract,
-      NO_DEPOSIT,
-      SINGLE_CALL_GAS,
-    )
-    .and(fungible_token::ft_balance_of(
-      env::current_account_id(),
-      &self.config.stable_coin_contract,
-      NO_DEPOSIT,
-      SINGLE_CALL_GAS,
-    ))
+  /// Overwrites the oldest snapshot once the ring buffer is full
+  pub(crate) fn internal_record_epoch_snapshot(&mut self, block_time: u64, deposit_rate: D128) {
+    let balance: Balance = self.state.stable_balance;
+    let snapshot = EpochSnapshot {
+      epoch: self.state.epoch_snapshot_count,
+      block_time,
+      exchange_rate: self.state.prev_exchange_rate,
+      deposit_rate,
+      borrow_rate: self.get_borrow_rate(
+        balance,
+        self.state.total_liabilities,
+        self.state.total_reserves,
+      ),
+      utilization_ratio: compute_utilization_ratio(
+        balance,
+        self.state.total_liabilities,
+        self.state.total_reserves,
+      ),
+      total_liabilities: self.state.total_liabilities,
+      total_reserves: self.state.total_reserves,
+    };
+
+    if self.collection.epoch_snapshots.len() < MAX_EPOCH_SNAPSHOTS {
+      self.collection.epoch_snapshots.push(&snapshot);
+    } else {
+      self
+        .collection
+        .epoch_snapshots
+        .replace(snapshot.epoch % MAX_EPOCH_SNAPSHOTS, &snapshot);
+    }
+    self.state.epoch_snapshot_count += 1;
   }
 }
+
+/// Block timestamp in seconds, the unit interest and rewards accrue in
+pub(crate) fn current_block_time() -> u64 {
+  env::block_timestamp() / SECOND_TO_NANO
+}
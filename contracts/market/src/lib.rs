@@ -1,31 +1,52 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::LookupMap;
-use near_sdk::json_types::{ValidAccountId, U128};
+use near_sdk::collections::{LookupMap, LookupSet, TreeMap, Vector};
+use near_sdk::json_types::{Base64VecU8, ValidAccountId, U128};
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::{
     assert_one_yocto, env, ext_contract, near_bindgen, AccountId, Balance, BlockHeight,
-    BorshStorageKey, Gas, PanicOnDefault, Promise, PromiseOrValue, PromiseResult,
+    BorshStorageKey, Gas, PanicOnDefault, Promise, PromiseOrValue, PromiseResult, StorageUsage,
 };
 
 use uint::construct_uint;
 
+use crate::atoken::{FungibleTokenMetadata, FT_METADATA_SPEC};
+use crate::borrow::{
+    compute_borrower_interest, compute_borrower_reward, compute_interest_raw, compute_reward,
+};
+use crate::deposit::compute_exchange_rate_raw;
 use crate::distribution_model::DistributionModelConfig;
+use crate::events::Event;
 // use crate::fraction::Fraction;
-use crate::interest_model::InterestModelConfig;
-use crate::math::D128;
-use crate::state::{BorrowerInfo, Collection, Config, State};
-use crate::utils::{ext_overseer, ext_self, fungible_token};
+use crate::interest_model::{compute_utilization_ratio, InterestModelConfig};
+use crate::internal::current_block_time;
+use crate::math::{Index, D128, DECIMAL, INDEX_DECIMAL, SECONDS_PER_YEAR};
+use crate::pause::{Operation, PauseFlags};
+use crate::state::{
+    BorrowCapacityResponse, BorrowerInfo, Collection, Config, EpochSnapshot, EpochStateResponse,
+    State,
+};
+use crate::utils::{
+    ext_distributor, ext_overseer, ext_self, fungible_token,
+};
 
+mod admin;
+mod atoken;
 mod borrow;
+mod delegation;
 mod deposit;
 mod distribution_model;
+mod events;
+mod flash_loan;
 mod fraction;
 mod fungible_token_handler;
 mod interest_model;
 mod internal;
 mod math;
+mod migration;
 mod owner;
+mod pause;
 mod state;
+mod storage;
 #[cfg(test)]
 mod testing;
 mod utils;
@@ -35,6 +56,18 @@ const NO_DEPOSIT: Balance = 0;
 
 const SINGLE_CALL_GAS: Gas = 100_000_000_000_000;
 
+const TRANSFER_CALL_GAS: Gas = 25_000_000_000_000;
+
+const CALLBACK_GAS: Gas = 50_000_000_000_000;
+
+// the transfer callback calls the receiver hook, then settles the flash loan
+const FLASH_LOAN_CALLBACK_GAS: Gas = SINGLE_CALL_GAS + 2 * TRANSFER_CALL_GAS;
+
+const SECOND_TO_NANO: u64 = 1_000_000_000;
+
+// 30 days of hourly epochs
+const MAX_EPOCH_SNAPSHOTS: u64 = 720;
+
 construct_uint! {
     /// 256-bit unsigned integer.
     pub struct U256(4);
@@ -43,6 +76,10 @@ construct_uint! {
 #[derive(BorshStorageKey, BorshSerialize)]
 pub(crate) enum StorageKey {
     BorrowerInfo,
+    ATokenBalance,
+    CreditAllowance,
+    EpochSnapshot,
+    FlashLoanReceiver,
 }
 
 #[near_bindgen]
@@ -53,6 +90,8 @@ pub struct Contract {
     collection: Collection,
     interest_model_config: InterestModelConfig,
     distribution_model_config: DistributionModelConfig,
+    account_storage_usage: StorageUsage,
+    atoken_metadata: FungibleTokenMetadata,
 }
 
 #[near_bindgen]
@@ -62,7 +101,10 @@ impl Contract {
         owner_id: AccountId,
         max_borrow_factor: D128,
         stable_coin_contract: AccountId,
+        atoken_metadata: FungibleTokenMetadata,
         overseer_contract: AccountId,
+        collector_contract: AccountId,
+        distributor_contract: AccountId,
 
         base_rate: D128,
         interest_multiplier: D128,
@@ -77,33 +119,57 @@ impl Contract {
             env::is_valid_account_id(owner_id.as_bytes()),
             "The owner account ID is invalid"
         );
+        assert_eq!(
+            atoken_metadata.spec, FT_METADATA_SPEC,
+            "The aToken metadata spec is invalid"
+        );
 
         let config = Config {
+            guardian_id: owner_id.clone(),
             owner_id,
+            pending_owner_id: None,
             max_borrow_factor,
             stable_coin_contract,
             overseer_contract,
+            collector_contract,
+            distributor_contract,
+            flash_loan_fee: D128::zero(),
+            global_borrow_cap: None,
+            account_borrow_cap: None,
         };
 
         let state = State {
             anc_emission_rate: D128::one(),
             total_liabilities: D128::zero(),
             total_reserves: D128::zero(),
-            last_interest_updated: 0,
-            global_interest_index: D128::zero(),
+            last_interest_updated: current_block_time(),
+            global_interest_index: Index::one(),
             prev_exchange_rate: D128::one(),
-            prev_stable_coin_total_supply: 0,
-            last_reward_updated: 0,
+            atoken_total_supply: 0,
+            last_reward_updated: current_block_time(),
             global_reward_index: D128::zero(),
+            // updated by the overseer on every epoch operation
+            target_deposit_rate: D128::one(),
+            stable_balance: 0,
+            flash_loan_due: 0,
+            flash_loan_repaid: 0,
+            epoch_snapshot_count: 0,
+            paused: PauseFlags::default(),
         };
 
         let collection = Collection {
-            borrower_info_map: LookupMap::new(StorageKey::BorrowerInfo),
+            borrower_info_map: TreeMap::new(StorageKey::BorrowerInfo),
+            atoken_balance_map: LookupMap::new(StorageKey::ATokenBalance),
+            credit_allowance_map: LookupMap::new(StorageKey::CreditAllowance),
+            epoch_snapshots: Vector::new(StorageKey::EpochSnapshot),
+            flash_loan_receivers: LookupSet::new(StorageKey::FlashLoanReceiver),
         };
 
         let interest_model_config = InterestModelConfig {
             base_rate,
             interest_multiplier,
+            optimal_utilization: D128::one(),
+            jump_multiplier: interest_multiplier,
         };
 
         let distribution_model_config = DistributionModelConfig {
@@ -113,13 +179,17 @@ impl Contract {
             decrement_multiplier,
         };
 
-        Self {
+        let mut this = Self {
             config,
             state,
             collection,
             interest_model_config,
             distribution_model_config,
-        }
+            account_storage_usage: 0,
+            atoken_metadata,
+        };
+        this.measure_account_storage_usage();
+        this
     }
 
     pub fn execute_epoch_operations(
@@ -130,19 +200,65 @@ impl Contract {
         distributed_intereset: U128,
     ) {
         self.assert_overseer();
-        fungible_token::ft_total_supply(
-            &self.config.stable_coin_contract,
-            NO_DEPOSIT,
-            SINGLE_CALL_GAS,
-        )
-        .then(ext_self::callback_execute_epoch_operations(
+
+        let stable_coin_total_supply: Balance = self.state.atoken_total_supply;
+        // the interest was credited by the overseer transfer, it is not a deposit
+        let balance: Balance = self.state.stable_balance.saturating_sub(distributed_intereset.0);
+
+        let borrow_rate = self.get_borrow_rate(
+            balance,
+            self.state.total_liabilities,
+            self.state.total_reserves,
+        );
+        let block_time = current_block_time();
+
+        compute_interest_raw(
+            &mut self.state,
+            block_time,
+            balance,
+            stable_coin_total_supply,
+            borrow_rate,
+            target_deposit_rate,
+        );
+
+        self.state.prev_exchange_rate = compute_exchange_rate_raw(
+            &self.state,
+            stable_coin_total_supply,
+            balance + distributed_intereset.0,
+        );
+        self.state.target_deposit_rate = target_deposit_rate;
+
+        compute_reward(&mut self.state, block_time);
+
+        let total_reserves = self.state.total_reserves.as_u128();
+
+        if total_reserves != 0 && balance > total_reserves {
+            self.state.total_reserves = self.state.total_reserves - total_reserves;
+            self.state.stable_balance -= total_reserves;
+
+            fungible_token::ft_transfer(
+                self.config.collector_contract.clone(),
+                U128::from(total_reserves),
+                None,
+                &self.config.stable_coin_contract,
+                1,
+                TRANSFER_CALL_GAS,
+            )
+            .then(ext_self::callback_transfer_reserves(
+                U128::from(total_reserves),
+                &env::current_account_id(),
+                NO_DEPOSIT,
+                TRANSFER_CALL_GAS,
+            ));
+        }
+
+        self.state.anc_emission_rate = self.get_emission_rate(
             deposit_rate,
             target_deposit_rate,
             threshold_deposit_rate,
-            distributed_intereset,
-            &env::current_account_id(),
-            NO_DEPOSIT,
-            SINGLE_CALL_GAS,
-        ));
+            self.state.anc_emission_rate,
+        );
+
+        self.internal_record_epoch_snapshot(block_time, deposit_rate);
     }
 }
@@ -12,6 +12,7 @@ construct_uint! {
 }
 
 pub const DECIMAL: u128 = 100_000_000; //1e8
+pub const SECONDS_PER_YEAR: u128 = 31_536_000;
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Copy, Clone, Debug)]
 #[serde(crate = "near_sdk::serde")]
@@ -72,6 +73,31 @@ impl D128 {
     pub fn as_u128(self) -> u128 {
         self.num.0 / DECIMAL
     }
+
+    /// Returns the interest on `amount` at this yearly rate over `seconds`,
+    /// scaled in one step so short periods do not round to zero
+    pub fn interest_over(self, amount: D128, seconds: u64) -> D128 {
+        let num: u128 = (U256::from(amount.num.0) * U256::from(self.num.0) * U256::from(seconds)
+            / U256::from(DECIMAL * SECONDS_PER_YEAR))
+        .as_u128();
+
+        Self::new(num)
+    }
+
+    /// Returns the yearly rate at which `prev` grew to `current` over `seconds`,
+    /// zero when it did not grow
+    pub fn yearly_growth(prev: D128, current: D128, seconds: u64) -> D128 {
+        if seconds == 0 || current <= prev {
+            return Self::zero();
+        }
+
+        let num: u128 = (U256::from(current.num.0 - prev.num.0)
+            * U256::from(DECIMAL * SECONDS_PER_YEAR)
+            / (U256::from(prev.num.0) * U256::from(seconds)))
+        .as_u128();
+
+        Self::new(num)
+    }
 }
 
 impl Add<D128> for D128 {
@@ -242,3 +268,88 @@ impl fmt::Display for D128 {
         }
     }
 }
+
+pub const INDEX_DECIMAL: u128 = 1_000_000_000_000_000_000; //1e18
+
+/// Interest index with 18 decimals, the growth of a yearly rate over a few
+/// seconds is below the precision of D128
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Copy, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Index {
+    pub num: U128,
+}
+
+impl Default for Index {
+    /// set default value to 1.0
+    fn default() -> Self {
+        Self {
+            num: U128::from(INDEX_DECIMAL),
+        }
+    }
+}
+
+impl Index {
+    /// num: multiplied by INDEX_DECIMAL constant
+    pub fn new(num: u128) -> Self {
+        Self { num: num.into() }
+    }
+
+    /// get Index 1.0
+    pub fn one() -> Self {
+        Self::default()
+    }
+
+    /// Returns the index grown at the yearly `rate` over `seconds`
+    pub fn accrue(self, rate: D128, seconds: u64) -> Self {
+        let growth: u128 = (U256::from(self.num.0) * U256::from(rate.num.0) * U256::from(seconds)
+            / U256::from(DECIMAL * SECONDS_PER_YEAR))
+        .as_u128();
+
+        Self::new(self.num.0 + growth)
+    }
+
+    /// Returns `amount` taken at the `since` index, grown to this index
+    pub fn grow(self, amount: u128, since: Index) -> u128 {
+        (U256::from(amount) * U256::from(self.num.0) / U256::from(since.num.0)).as_u128()
+    }
+}
+
+impl Div<Index> for D128 {
+    type Output = D128;
+    #[inline]
+    fn div(self, other: Index) -> D128 {
+        let num: u128 = (U256::from(self.num.0) * U256::from(INDEX_DECIMAL)
+            / U256::from(other.num.0))
+        .as_u128();
+
+        D128::new(num)
+    }
+}
+
+impl Div<Index> for u128 {
+    type Output = D128;
+    #[inline]
+    fn div(self, other: Index) -> D128 {
+        D128::new(self * DECIMAL) / other
+    }
+}
+
+impl Ord for Index {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.num.0.cmp(&other.num.0)
+    }
+}
+
+impl PartialOrd for Index {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Eq for Index {}
+
+impl PartialEq for Index {
+    fn eq(&self, other: &Self) -> bool {
+        self.num.0 == other.num.0
+    }
+}
@@ -0,0 +1,222 @@
+use crate::*;
+
+/// `Config` of the deployed market
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OldConfig {
+  pub owner_id: AccountId,
+  pub stable_coin_contract: AccountId,
+  pub max_borrow_factor: D128,
+  pub overseer_contract: AccountId,
+}
+
+/// `State` of the deployed market, its times are block heights and its rates are per block
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OldState {
+  pub anc_emission_rate: D128,
+  pub total_liabilities: D128,
+  pub total_reserves: D128,
+  pub last_interest_updated: BlockHeight,
+  pub global_interest_index: D128,
+  pub prev_exchange_rate: D128,
+  pub prev_stable_coin_total_supply: u128,
+  pub last_reward_updated: BlockHeight,
+  pub global_reward_index: D128,
+}
+
+/// `BorrowerInfo` of the deployed market, its interest index has 8 decimals
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OldBorrowerInfo {
+  pub interest_index: D128,
+  pub reward_index: D128,
+  pub loan_amount: Balance,
+  pub pending_rewards: D128,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OldCollection {
+  pub borrower_info_map: LookupMap<AccountId, OldBorrowerInfo>,
+}
+
+/// `InterestModelConfig` of the deployed market, without the kink
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OldInterestModelConfig {
+  pub base_rate: D128,
+  pub interest_multiplier: D128,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OldContract {
+  pub config: OldConfig,
+  pub state: OldState,
+  pub collection: OldCollection,
+  pub interest_model_config: OldInterestModelConfig,
+  pub distribution_model_config: DistributionModelConfig,
+}
+
+#[near_bindgen]
+impl Contract {
+  /// Migrates the deployed market. Its borrower map cannot be iterated, so
+  /// `borrowers` lists every account in it; `atoken_balances` is a snapshot of
+  /// the old aToken contract, taken once it stopped accepting transfers, and
+  /// `stable_balance` the stable coin held by the market. Block heights and per
+  /// block rates are converted with `seconds_per_block`, the average block time
+  /// since the deployment. The migrated accounts are registered without a
+  /// storage deposit.
+  #[private]
+  #[init(ignore_state)]
+  pub fn migrate(
+    collector_contract: AccountId,
+    distributor_contract: AccountId,
+    atoken_metadata: FungibleTokenMetadata,
+    atoken_balances: Vec<(AccountId, U128)>,
+    borrowers: Vec<AccountId>,
+    stable_balance: U128,
+    seconds_per_block: D128,
+  ) -> Self {
+    let mut old: OldContract = env::state_read().expect("Old state doesn't exist");
+    assert_eq!(
+      atoken_metadata.spec, FT_METADATA_SPEC,
+      "The aToken metadata spec is invalid"
+    );
+    assert!(
+      seconds_per_block > D128::zero(),
+      "seconds_per_block must be positive"
+    );
+
+    let config = Config {
+      guardian_id: old.config.owner_id.clone(),
+      owner_id: old.config.owner_id,
+      pending_owner_id: None,
+      stable_coin_contract: old.config.stable_coin_contract,
+      max_borrow_factor: old.config.max_borrow_factor,
+      overseer_contract: old.config.overseer_contract,
+      collector_contract,
+      distributor_contract,
+      flash_loan_fee: D128::zero(),
+      global_borrow_cap: None,
+      account_borrow_cap: None,
+    };
+
+    let mut collection = Collection {
+      borrower_info_map: TreeMap::new(StorageKey::BorrowerInfo),
+      atoken_balance_map: LookupMap::new(StorageKey::ATokenBalance),
+      credit_allowance_map: LookupMap::new(StorageKey::CreditAllowance),
+      epoch_snapshots: Vector::new(StorageKey::EpochSnapshot),
+      flash_loan_receivers: LookupSet::new(StorageKey::FlashLoanReceiver),
+    };
+
+    // the borrowers move to an ordered map under the same prefix
+    for borrower in borrowers.iter() {
+      let borrower_info: OldBorrowerInfo = match old.collection.borrower_info_map.remove(borrower) {
+        Some(value) => value,
+        None => env::panic(format!("The account {} is not a borrower", borrower).as_bytes()),
+      };
+      collection.borrower_info_map.insert(
+        borrower,
+        &BorrowerInfo {
+          interest_index: to_index(borrower_info.interest_index),
+          reward_index: borrower_info.reward_index,
+          loan_amount: borrower_info.loan_amount,
+          pending_rewards: borrower_info.pending_rewards,
+        },
+      );
+      collection.atoken_balance_map.insert(borrower, &0);
+    }
+
+    let global_interest_index: Index = to_index(old.state.global_interest_index);
+    let mut atoken_total_supply: Balance = 0;
+    for (account_id, balance) in atoken_balances.iter() {
+      if collection.atoken_balance_map.get(account_id).unwrap_or(0) > 0 {
+        env::panic(format!("Duplicate aToken balance of {}", account_id).as_bytes());
+      }
+      collection.atoken_balance_map.insert(account_id, &balance.0);
+      atoken_total_supply += balance.0;
+
+      // an aToken holder is registered like any other account
+      if collection.borrower_info_map.get(account_id).is_none() {
+        collection.borrower_info_map.insert(
+          account_id,
+          &BorrowerInfo {
+            interest_index: global_interest_index,
+            reward_index: old.state.global_reward_index,
+            loan_amount: 0,
+            pending_rewards: D128::zero(),
+          },
+        );
+      }
+    }
+
+    let block_time: u64 = current_block_time();
+    let state = State {
+      anc_emission_rate: old.state.anc_emission_rate / seconds_per_block,
+      total_liabilities: old.state.total_liabilities,
+      total_reserves: old.state.total_reserves,
+      last_interest_updated: to_block_time(
+        old.state.last_interest_updated,
+        block_time,
+        seconds_per_block,
+      ),
+      global_interest_index,
+      prev_exchange_rate: old.state.prev_exchange_rate,
+      atoken_total_supply,
+      last_reward_updated: to_block_time(
+        old.state.last_reward_updated,
+        block_time,
+        seconds_per_block,
+      ),
+      global_reward_index: old.state.global_reward_index,
+      // updated by the overseer on every epoch operation
+      target_deposit_rate: D128::one(),
+      stable_balance: stable_balance.0,
+      flash_loan_due: 0,
+      flash_loan_repaid: 0,
+      epoch_snapshot_count: 0,
+      paused: PauseFlags::default(),
+    };
+
+    // per block rates become yearly ones, the kink is disabled as in `new`
+    let blocks_per_year: D128 = D128::new_exp(SECONDS_PER_YEAR, 0) / seconds_per_block;
+    let interest_model_config = InterestModelConfig {
+      base_rate: old.interest_model_config.base_rate * blocks_per_year,
+      interest_multiplier: old.interest_model_config.interest_multiplier * blocks_per_year,
+      optimal_utilization: D128::one(),
+      jump_multiplier: old.interest_model_config.interest_multiplier * blocks_per_year,
+    };
+
+    // the emission bounds are amounts per block, as the emission rate
+    let distribution_model_config = DistributionModelConfig {
+      emission_cap: old.distribution_model_config.emission_cap / seconds_per_block,
+      emission_floor: old.distribution_model_config.emission_floor / seconds_per_block,
+      increment_multiplier: old.distribution_model_config.increment_multiplier,
+      decrement_multiplier: old.distribution_model_config.decrement_multiplier,
+    };
+
+    let mut this = Self {
+      config,
+      state,
+      collection,
+      interest_model_config,
+      distribution_model_config,
+      account_storage_usage: 0,
+      atoken_metadata,
+    };
+    this.measure_account_storage_usage();
+    this
+  }
+}
+
+/// Widens an interest index from the 8 decimals of D128, the deployed market
+/// started its index at zero
+fn to_index(index: D128) -> Index {
+  if index == D128::zero() {
+    return Index::one();
+  }
+  Index::new(index.num.0 * (INDEX_DECIMAL / DECIMAL))
+}
+
+/// Block time in seconds of the block at `block_height`
+fn to_block_time(block_height: BlockHeight, block_time: u64, seconds_per_block: D128) -> u64 {
+  let passed_blocks: BlockHeight = env::block_index().saturating_sub(block_height);
+  let passed_time: u64 = seconds_per_block.mul_int(passed_blocks as u128) as u64;
+  block_time.saturating_sub(passed_time)
+}
@@ -5,17 +5,16 @@ impl Contract {
   #[payable]
   pub fn update_config(
     &mut self,
-    owner_id: Option<AccountId>,
     stable_coin_contract: Option<AccountId>,
     max_borrow_factor: Option<D128>,
     overseer_contract: Option<AccountId>,
+    collector_contract: Option<AccountId>,
+    distributor_contract: Option<AccountId>,
+    flash_loan_fee: Option<D128>,
   ) {
     self.assert_owner();
     assert_one_yocto();
 
-    if let Some(owner_id) = owner_id {
-      self.config.owner_id = owner_id;
-    }
     if let Some(stable_coin_contract) = stable_coin_contract {
       self.config.stable_coin_contract = stable_coin_contract;
     }
@@ -25,6 +24,17 @@ impl Contract {
     if let Some(overseer_contract) = overseer_contract {
       self.config.overseer_contract = overseer_contract;
     }
+    if let Some(collector_contract) = collector_contract {
+      self.config.collector_contract = collector_contract;
+    }
+    if let Some(distributor_contract) = distributor_contract {
+      self.config.distributor_contract = distributor_contract;
+    }
+    if let Some(flash_loan_fee) = flash_loan_fee {
+      self.config.flash_loan_fee = flash_loan_fee;
+    }
+
+    Event::UpdateConfig(&self.config).emit();
   }
 
   #[payable]
@@ -32,6 +42,8 @@ impl Contract {
     &mut self,
     base_rate: Option<D128>,
     interest_multiplier: Option<D128>,
+    optimal_utilization: Option<D128>,
+    jump_multiplier: Option<D128>,
   ) {
     assert_one_yocto();
     self.assert_owner();
@@ -43,6 +55,17 @@ impl Contract {
     if let Some(interest_multiplier) = interest_multiplier {
       self.interest_model_config.interest_multiplier = interest_multiplier;
     }
+
+    if let Some(optimal_utilization) = optimal_utilization {
+      Self::assert_optimal_utilization(optimal_utilization);
+      self.interest_model_config.optimal_utilization = optimal_utilization;
+    }
+
+    if let Some(jump_multiplier) = jump_multiplier {
+      self.interest_model_config.jump_multiplier = jump_multiplier;
+    }
+
+    Event::UpdateInterestModelConfig(&self.interest_model_config).emit();
   }
 
   #[payable]
@@ -71,5 +94,51 @@ impl Contract {
     if let Some(decrement_multiplier) = decrement_multiplier {
       self.distribution_model_config.decrement_multiplier = decrement_multiplier;
     }
+
+    Event::UpdateDistributionModelConfig(&self.distribution_model_config).emit();
+  }
+
+  /// Overwrites both caps, passing `None` removes a cap
+  #[payable]
+  pub fn update_borrow_caps(
+    &mut self,
+    global_borrow_cap: Option<U128>,
+    account_borrow_cap: Option<U128>,
+  ) {
+    assert_one_yocto();
+    self.assert_owner();
+
+    self.config.global_borrow_cap = global_borrow_cap.map(|cap| cap.0);
+    self.config.account_borrow_cap = account_borrow_cap.map(|cap| cap.0);
+
+    Event::UpdateConfig(&self.config).emit();
+  }
+
+  /// Trusts `receiver_id` to repay its flash loans, which can't be enforced
+  /// once the stable coin has left the market
+  #[payable]
+  pub fn add_flash_loan_receiver(&mut self, receiver_id: ValidAccountId) {
+    assert_one_yocto();
+    self.assert_owner();
+    let receiver_id: AccountId = receiver_id.into();
+    self.collection.flash_loan_receivers.insert(&receiver_id);
+
+    Event::AddFlashLoanReceiver {
+      receiver_id: &receiver_id,
+    }
+    .emit();
+  }
+
+  #[payable]
+  pub fn remove_flash_loan_receiver(&mut self, receiver_id: ValidAccountId) {
+    assert_one_yocto();
+    self.assert_owner();
+    let receiver_id: AccountId = receiver_id.into();
+    self.collection.flash_loan_receivers.remove(&receiver_id);
+
+    Event::RemoveFlashLoanReceiver {
+      receiver_id: &receiver_id,
+    }
+    .emit();
   }
 }
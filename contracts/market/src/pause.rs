@@ -0,0 +1,48 @@
+use crate::*;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum Operation {
+  Deposit,
+  Borrow,
+  Redeem,
+}
+
+/// Operations halted by the guardian, `true` means paused
+#[derive(
+  BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq,
+)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PauseFlags {
+  pub deposit: bool,
+  pub borrow: bool,
+  pub redeem: bool,
+}
+
+impl PauseFlags {
+  pub(crate) fn get(&self, operation: Operation) -> bool {
+    match operation {
+      Operation::Deposit => self.deposit,
+      Operation::Borrow => self.borrow,
+      Operation::Redeem => self.redeem,
+    }
+  }
+
+  pub(crate) fn get_mut(&mut self, operation: Operation) -> &mut bool {
+    match operation {
+      Operation::Deposit => &mut self.deposit,
+      Operation::Borrow => &mut self.borrow,
+      Operation::Redeem => &mut self.redeem,
+    }
+  }
+}
+
+impl Contract {
+  pub(crate) fn pause_flags(&self) -> &PauseFlags {
+    &self.state.paused
+  }
+
+  pub(crate) fn pause_flags_mut(&mut self) -> &mut PauseFlags {
+    &mut self.state.paused
+  }
+}
@@ -4,9 +4,19 @@ use crate::*;
 #[serde(crate = "near_sdk::serde")]
 pub struct Config {
   pub owner_id: AccountId,
+  // proposed owner, set until it accepts the ownership
+  pub pending_owner_id: Option<AccountId>,
+  // can pause operations, only the owner can unpause them
+  pub guardian_id: AccountId,
   pub stable_coin_contract: AccountId,
   pub max_borrow_factor: D128,
   pub overseer_contract: AccountId,
+  pub collector_contract: AccountId,
+  pub distributor_contract: AccountId,
+  pub flash_loan_fee: D128,
+  // hard limits on borrowing, `None` means uncapped
+  pub global_borrow_cap: Option<Balance>,
+  pub account_borrow_cap: Option<Balance>,
 }
 
 #[derive(
@@ -17,42 +27,91 @@ pub struct State {
   pub anc_emission_rate: D128,
   pub total_liabilities: D128,
   pub total_reserves: D128,
-  pub last_interest_updated: BlockHeight,
-  pub global_interest_index: D128,
+  // block times are in seconds and all rates are yearly
+  pub last_interest_updated: u64,
+  pub global_interest_index: Index,
   pub prev_exchange_rate: D128,
-  pub prev_stable_coin_total_supply: u128,
-  pub last_reward_updated: BlockHeight,
+  // aToken minted by the market itself
+  pub atoken_total_supply: Balance,
+  pub last_reward_updated: u64,
   pub global_reward_index: D128,
+  pub target_deposit_rate: D128,
+  // stable coin held by the market, tracked on every token movement
+  pub stable_balance: Balance,
+  // principal plus fee owed by the flash loan in progress, zero when idle
+  pub flash_loan_due: Balance,
+  pub flash_loan_repaid: Balance,
+  // epoch snapshots recorded so far, including the ones overwritten in the ring buffer
+  pub epoch_snapshot_count: u64,
+  pub paused: PauseFlags,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(crate = "near_sdk::serde")]
 pub struct BorrowerInfo {
-  pub interest_index: D128,
+  pub interest_index: Index,
   pub reward_index: D128,
   pub loan_amount: Balance,
   pub pending_rewards: D128,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EpochStateResponse {
+  pub exchange_rate: D128,
+  pub atoken_supply: U128,
+  pub deposit_rate: D128,
+  pub total_liabilities: D128,
+  pub total_reserves: D128,
+}
+
+/// Market rates at the end of an epoch operation, `epoch` counts from zero
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EpochSnapshot {
+  pub epoch: u64,
+  pub block_time: u64,
+  pub exchange_rate: D128,
+  pub deposit_rate: D128,
+  pub borrow_rate: D128,
+  pub utilization_ratio: D128,
+  pub total_liabilities: D128,
+  pub total_reserves: D128,
+}
+
+/// Remaining borrow capacity under the caps, `None` when uncapped
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BorrowCapacityResponse {
+  pub global: Option<U128>,
+  pub account: Option<U128>,
+}
+
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct Collection {
-  pub borrower_info_map: LookupMap<AccountId, BorrowerInfo>,
+  // ordered by account, so borrowers can be paged with an account cursor
+  pub borrower_info_map: TreeMap<AccountId, BorrowerInfo>,
+  pub atoken_balance_map: LookupMap<AccountId, Balance>,
+  // (owner, delegate) => stable coin the delegate can still borrow on behalf of the owner
+  pub credit_allowance_map: LookupMap<(AccountId, AccountId), Balance>,
+  // ring buffer of the last MAX_EPOCH_SNAPSHOTS epochs, indexed by epoch % MAX_EPOCH_SNAPSHOTS
+  pub epoch_snapshots: Vector<EpochSnapshot>,
+  // receivers trusted by the owner to repay their flash loans
+  pub flash_loan_receivers: LookupSet<AccountId>,
 }
 
-#[near_bindgen]
 impl Contract {
-  pub fn add_borrower_info_map(&mut self, key: &String, value: &BorrowerInfo) {
+  pub(crate) fn add_borrower_info_map(&mut self, key: &String, value: &BorrowerInfo) {
     self.collection.borrower_info_map.insert(&key, value);
   }
+}
 
+#[near_bindgen]
+impl Contract {
   pub fn get_borrower_info_map(&self, key: &String) -> BorrowerInfo {
     match self.collection.borrower_info_map.get(&key) {
-      Some(value) => {
-        let log_message = format!("Value from LookupMap is {:?}", value.clone());
-        env::log(log_message.as_bytes());
-        value
-      }
-      None => env::panic("".as_bytes()),
+      Some(value) => value,
+      None => env::panic(format!("The account {} is not registered", key).as_bytes()),
     }
   }
 }
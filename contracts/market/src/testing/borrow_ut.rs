@@ -1,6 +1,7 @@
 use near_sdk::test_utils::{accounts, VMContextBuilder};
-use near_sdk::{testing_env, MockedBlockchain};
+use near_sdk::{testing_env, MockedBlockchain, RuntimeFeesConfig, VMConfig};
 
+use crate::fungible_token_handler::FungibleTokenReceiver;
 use crate::*;
 
 pub fn setup_contract() -> (VMContextBuilder, Contract) {
@@ -13,7 +14,18 @@ pub fn setup_contract() -> (VMContextBuilder, Contract) {
     AccountId::from("owner"),
     D128::zero(),
     AccountId::from("stable_coin"),
+    FungibleTokenMetadata {
+      spec: FT_METADATA_SPEC.to_string(),
+      name: String::from("aUSD"),
+      symbol: String::from("aUSD"),
+      icon: None,
+      reference: None,
+      reference_hash: None,
+      decimals: 6,
+    },
     AccountId::from("overseer"),
+    AccountId::from("collector"),
+    AccountId::from("distributor"),
     D128::new_exp(1, -1),
     D128::new_exp(1, -1),
     D128::new_exp(100, 0),
@@ -26,28 +38,34 @@ pub fn setup_contract() -> (VMContextBuilder, Contract) {
 
 #[test]
 fn proper_compute_borrower_interest() {
-  let (context, mut contract) = setup_contract();
+  let (_, mut contract) = setup_contract();
   let mock_state = State {
     total_liabilities: D128::new(1000000u128 * 100_000_000),
     total_reserves: D128::zero(),
-    last_interest_updated: context.context.block_index,
-    last_reward_updated: context.context.block_index,
-    global_interest_index: D128::one(),
+    last_interest_updated: current_block_time(),
+    last_reward_updated: current_block_time(),
+    global_interest_index: Index::one(),
     global_reward_index: D128::zero(),
     anc_emission_rate: D128::one(),
-    prev_stable_coin_total_supply: 0,
+    atoken_total_supply: 0,
     prev_exchange_rate: D128::one(),
+    target_deposit_rate: D128::one(),
+    stable_balance: 0,
+    flash_loan_due: 0,
+    flash_loan_repaid: 0,
+    epoch_snapshot_count: 0,
+    paused: PauseFlags::default(),
   };
   let mut liability1 = BorrowerInfo {
-    interest_index: D128::one(),
+    interest_index: Index::one(),
     reward_index: D128::zero(),
     loan_amount: 0,
     pending_rewards: D128::zero(),
   };
   contract.state = mock_state;
-  contract.compute_borrower_interest(&mut liability1);
+  compute_borrower_interest(&contract.state, &mut liability1);
   let liability2 = BorrowerInfo {
-    interest_index: D128::one(),
+    interest_index: Index::one(),
     reward_index: D128::zero(),
     loan_amount: 0,
     pending_rewards: D128::zero(),
@@ -57,24 +75,30 @@ fn proper_compute_borrower_interest() {
   let mock_state2 = State {
     total_liabilities: D128::new(300000 * 100_000_000),
     total_reserves: D128::new(1000 * 100_000_000),
-    last_interest_updated: context.context.block_index,
-    last_reward_updated: context.context.block_index,
-    global_interest_index: D128::new(2 * 100_000_000),
+    last_interest_updated: current_block_time(),
+    last_reward_updated: current_block_time(),
+    global_interest_index: Index::new(2 * INDEX_DECIMAL),
     global_reward_index: D128::zero(),
     anc_emission_rate: D128::zero(),
-    prev_stable_coin_total_supply: 0,
+    atoken_total_supply: 0,
     prev_exchange_rate: D128::one(),
+    target_deposit_rate: D128::one(),
+    stable_balance: 0,
+    flash_loan_due: 0,
+    flash_loan_repaid: 0,
+    epoch_snapshot_count: 0,
+    paused: PauseFlags::default(),
   };
   let mut liability3 = BorrowerInfo {
-    interest_index: D128::new(4 * 100_000_000),
+    interest_index: Index::new(4 * INDEX_DECIMAL),
     reward_index: D128::zero(),
     loan_amount: 80,
     pending_rewards: D128::zero(),
   };
   contract.state = mock_state2;
-  contract.compute_borrower_interest(&mut liability3);
+  compute_borrower_interest(&contract.state, &mut liability3);
   let liability4 = BorrowerInfo {
-    interest_index: D128::new(2 * 100_000_000),
+    interest_index: Index::new(2 * INDEX_DECIMAL),
     reward_index: D128::zero(),
     loan_amount: 40,
     pending_rewards: D128::zero(),
@@ -82,46 +106,113 @@ fn proper_compute_borrower_interest() {
   assert_eq!(liability3, liability4);
 }
 
+#[test]
+fn interest_at_yearly_rates() {
+  let mut state = State {
+    total_liabilities: D128::new_exp(1000000, 0),
+    total_reserves: D128::zero(),
+    last_interest_updated: 100,
+    last_reward_updated: 100,
+    global_interest_index: Index::one(),
+    global_reward_index: D128::zero(),
+    anc_emission_rate: D128::zero(),
+    atoken_total_supply: 2000000u128,
+    prev_exchange_rate: D128::one(),
+    target_deposit_rate: D128::new_exp(5, -2),
+    stable_balance: 1000000u128,
+    flash_loan_due: 0,
+    flash_loan_repaid: 0,
+    epoch_snapshot_count: 0,
+    paused: PauseFlags::default(),
+  };
+
+  // one second at 15% a year: 1000000 * 0.15 / 31536000 = 0.00475646
+  compute_interest_raw(
+    &mut state,
+    101,
+    1000000u128,
+    2000000u128,
+    D128::new_exp(15, -2),
+    D128::new_exp(5, -2),
+  );
+  assert_eq!(D128::new(1000000_00475646), state.total_liabilities);
+  assert_eq!(
+    Index::new(1_000000004756468797),
+    state.global_interest_index
+  );
+  // the depositors keep 2000000 * 0.05 / 31536000 = 0.00317097
+  assert_eq!(D128::new(158549), state.total_reserves);
+
+  // a day at 15% a year grows a loan by 0.041%
+  state.global_interest_index = Index::one().accrue(D128::new_exp(15, -2), 86400);
+  let mut liability = BorrowerInfo {
+    interest_index: Index::one(),
+    reward_index: D128::zero(),
+    loan_amount: 1000000u128,
+    pending_rewards: D128::zero(),
+  };
+  compute_borrower_interest(&state, &mut liability);
+  assert_eq!(1000410u128, liability.loan_amount);
+  assert_eq!(state.global_interest_index, liability.interest_index);
+}
+
 #[test]
 fn proper_compute_interest() {
   let (mut context, mut contract) = setup_contract();
 
   let mock_config = Config {
     owner_id: AccountId::from("owner"),
+    pending_owner_id: None,
+    guardian_id: AccountId::from("owner"),
     stable_coin_contract: AccountId::from("stable_coin"),
     max_borrow_factor: D128::one(),
     overseer_contract: AccountId::from("overseer"),
+    collector_contract: AccountId::from("collector"),
+    distributor_contract: AccountId::from("distributor"),
+    flash_loan_fee: D128::zero(),
+    global_borrow_cap: None,
+    account_borrow_cap: None,
   };
 
   let mut mock_state = State {
     total_liabilities: D128::new(1000000u128 * 100_000_000),
     total_reserves: D128::zero(),
-    last_interest_updated: context.context.block_index,
-    last_reward_updated: context.context.block_index,
-    global_interest_index: D128::one(),
+    last_interest_updated: current_block_time(),
+    last_reward_updated: current_block_time(),
+    global_interest_index: Index::one(),
     global_reward_index: D128::zero(),
     anc_emission_rate: D128::one(),
-    prev_stable_coin_total_supply: 0,
+    atoken_total_supply: 0,
     prev_exchange_rate: D128::one(),
+    target_deposit_rate: D128::one(),
+    stable_balance: 0,
+    flash_loan_due: 0,
+    flash_loan_repaid: 0,
+    epoch_snapshot_count: 0,
+    paused: PauseFlags::default(),
   };
   contract.state = mock_state;
 
-  let mock_deposit_amount = Some(1000u128);
-
-  contract.compute_interest(context.context.block_index, mock_deposit_amount);
+  contract.compute_interest(current_block_time());
 
   assert_eq!(
     mock_state,
     State {
       total_liabilities: D128::new(1000000u128 * 100_000_000),
       total_reserves: D128::zero(),
-      last_interest_updated: context.context.block_index,
-      last_reward_updated: context.context.block_index,
-      global_interest_index: D128::one(),
+      last_interest_updated: current_block_time(),
+      last_reward_updated: current_block_time(),
+      global_interest_index: Index::one(),
       global_reward_index: D128::zero(),
       anc_emission_rate: D128::one(),
-      prev_stable_coin_total_supply: 0,
+      atoken_total_supply: 0,
       prev_exchange_rate: D128::one(),
+      target_deposit_rate: D128::one(),
+      stable_balance: 0,
+      flash_loan_due: 0,
+      flash_loan_repaid: 0,
+      epoch_snapshot_count: 0,
+      paused: PauseFlags::default(),
     }
   );
 
@@ -147,3 +238,431 @@ fn proper_compute_interest() {
   //   }
   // );
 }
+
+#[test]
+fn proper_borrower_infos() {
+  let (_, mut contract) = setup_contract();
+
+  for (borrower, loan_amount) in vec![("carol", 300u128), ("alice", 100u128), ("bob", 200u128)] {
+    contract.add_borrower_info_map(
+      &AccountId::from(borrower),
+      &BorrowerInfo {
+        interest_index: Index::one(),
+        reward_index: D128::zero(),
+        loan_amount,
+        pending_rewards: D128::zero(),
+      },
+    );
+  }
+
+  // borrowers are listed by account
+  let borrower_infos = contract.get_borrower_infos(None, Some(2), None);
+  assert_eq!(2, borrower_infos.len());
+  assert_eq!(AccountId::from("alice"), borrower_infos[0].0);
+  assert_eq!(100u128, borrower_infos[0].1.loan_amount);
+  assert_eq!(AccountId::from("bob"), borrower_infos[1].0);
+  assert_eq!(200u128, borrower_infos[1].1.loan_amount);
+
+  // a borrower leaving between two pages doesn't skip the next one
+  contract
+    .collection
+    .borrower_info_map
+    .remove(&AccountId::from("alice"));
+  let borrower_infos = contract.get_borrower_infos(Some(AccountId::from("bob")), None, None);
+  assert_eq!(1, borrower_infos.len());
+  assert_eq!(AccountId::from("carol"), borrower_infos[0].0);
+  assert_eq!(300u128, borrower_infos[0].1.loan_amount);
+
+  assert!(contract
+    .get_borrower_infos(Some(AccountId::from("carol")), None, None)
+    .is_empty());
+}
+
+#[test]
+fn proper_loan_amount() {
+  let (_, mut contract) = setup_contract();
+  contract.add_borrower_info_map(
+    &AccountId::from("bob"),
+    &BorrowerInfo {
+      interest_index: Index::one(),
+      reward_index: D128::zero(),
+      loan_amount: 200u128,
+      pending_rewards: D128::zero(),
+    },
+  );
+
+  assert_eq!(
+    U128::from(200u128),
+    contract.get_loan_amount(AccountId::from("bob"), None)
+  );
+  // unregistered accounts have no debt, instead of failing the overseer query
+  assert_eq!(
+    U128::from(0u128),
+    contract.get_loan_amount(AccountId::from("carol"), None)
+  );
+}
+
+#[test]
+fn proper_claim_reward() {
+  let (mut context, mut contract) = setup_contract();
+  let borrower = accounts(1).to_string();
+  contract.add_borrower_info_map(
+    &borrower,
+    &BorrowerInfo {
+      interest_index: Index::one(),
+      reward_index: D128::zero(),
+      loan_amount: 100,
+      pending_rewards: D128::new_exp(150, 0),
+    },
+  );
+
+  testing_env!(context.predecessor_account_id(accounts(1)).build());
+  contract.claim_reward();
+  // the claim is deducted while the distributor spends it
+  assert_eq!(
+    D128::zero(),
+    contract.get_borrower_info_map(&borrower).pending_rewards
+  );
+
+  testing_env!(
+    context
+      .predecessor_account_id(accounts(0))
+      .current_account_id(accounts(0))
+      .build(),
+    VMConfig::default(),
+    RuntimeFeesConfig::default(),
+    Default::default(),
+    vec![PromiseResult::Failed]
+  );
+
+  // rewards are restored when the distributor fails to spend
+  contract.callback_claim_reward(borrower.clone(), U128::from(150u128));
+  assert_eq!(
+    D128::new_exp(150, 0),
+    contract.get_borrower_info_map(&borrower).pending_rewards
+  );
+
+  testing_env!(context.predecessor_account_id(accounts(1)).build());
+  contract.claim_reward();
+
+  testing_env!(
+    context.predecessor_account_id(accounts(0)).build(),
+    VMConfig::default(),
+    RuntimeFeesConfig::default(),
+    Default::default(),
+    vec![PromiseResult::Successful(vec![])]
+  );
+
+  contract.callback_claim_reward(borrower.clone(), U128::from(150u128));
+  assert_eq!(
+    D128::zero(),
+    contract.get_borrower_info_map(&borrower).pending_rewards
+  );
+}
+
+#[test]
+#[should_panic(expected = "No rewards to claim")]
+fn claim_reward_twice() {
+  let (mut context, mut contract) = setup_contract();
+  contract.add_borrower_info_map(
+    &accounts(1).to_string(),
+    &BorrowerInfo {
+      interest_index: Index::one(),
+      reward_index: D128::zero(),
+      loan_amount: 100,
+      pending_rewards: D128::new_exp(150, 0),
+    },
+  );
+
+  testing_env!(context.predecessor_account_id(accounts(1)).build());
+  contract.claim_reward();
+  contract.claim_reward();
+}
+
+#[test]
+fn proper_reconcile_stable_balance() {
+  let (mut context, mut contract) = setup_contract();
+  contract.state.stable_balance = 1000;
+
+  testing_env!(
+    context
+      .predecessor_account_id(accounts(0))
+      .current_account_id(accounts(0))
+      .build(),
+    VMConfig::default(),
+    RuntimeFeesConfig::default(),
+    Default::default(),
+    vec![PromiseResult::Successful(
+      near_sdk::serde_json::to_vec(&U128::from(1500u128)).unwrap()
+    )]
+  );
+
+  contract.callback_reconcile_stable_balance();
+  assert_eq!(1500u128, contract.get_balance());
+}
+
+#[test]
+#[should_panic(expected = "Max Borrow Factor Reached")]
+fn borrow_more_than_max_borrow_factor() {
+  let (_, mut contract) = setup_contract();
+  contract.config.max_borrow_factor = D128::new_exp(5, -1);
+  contract.state.stable_balance = 1000;
+  contract.state.total_reserves = D128::new_exp(100, 0);
+
+  // (1000 + 0 - 100) * 0.5 = 450
+  contract.assert_max_borrow_factor(contract.state.stable_balance, 451);
+}
+
+#[test]
+#[should_panic(expected = "Account Borrow Cap Reached")]
+fn borrow_more_than_account_borrow_cap() {
+  let (_, mut contract) = setup_contract();
+  contract.config.global_borrow_cap = Some(10000);
+  contract.config.account_borrow_cap = Some(1000);
+
+  let liability = BorrowerInfo {
+    interest_index: Index::one(),
+    reward_index: D128::zero(),
+    loan_amount: 900,
+    pending_rewards: D128::zero(),
+  };
+
+  contract.assert_borrow_cap(&liability, 100);
+  contract.assert_borrow_cap(&liability, 101);
+}
+
+#[test]
+fn proper_borrow_capacity() {
+  let (_, mut contract) = setup_contract();
+  contract.state.total_liabilities = D128::new_exp(3000, 0);
+  contract.add_borrower_info_map(
+    &accounts(1).to_string(),
+    &BorrowerInfo {
+      interest_index: Index::one(),
+      reward_index: D128::zero(),
+      loan_amount: 1500,
+      pending_rewards: D128::zero(),
+    },
+  );
+
+  let capacity = contract.get_borrow_capacity(Some(accounts(1).to_string()), Some(0));
+  assert_eq!(None, capacity.global);
+  assert_eq!(None, capacity.account);
+
+  contract.config.global_borrow_cap = Some(10000);
+  contract.config.account_borrow_cap = Some(1000);
+
+  let capacity = contract.get_borrow_capacity(Some(accounts(1).to_string()), Some(0));
+  assert_eq!(Some(U128(7000)), capacity.global);
+  assert_eq!(Some(U128(0)), capacity.account);
+}
+
+#[test]
+fn compute_interest_on_stable_ledger() {
+  let (_, mut contract) = setup_contract();
+  contract.state.total_liabilities = D128::new_exp(1000, 0);
+  contract.state.stable_balance = 1000;
+  contract.state.atoken_total_supply = 1000;
+
+  // accrued synchronously from the ledger, no balance query is needed
+  contract.compute_interest(100);
+  assert_eq!(100, contract.state.last_interest_updated);
+  assert!(contract.state.total_liabilities > D128::new_exp(1000, 0));
+  assert_eq!(1000, contract.state.stable_balance);
+}
+
+#[test]
+fn revert_borrow_on_failed_transfer() {
+  let (mut context, mut contract) = setup_contract();
+  contract.state.total_liabilities = D128::new_exp(1500, 0);
+  contract.state.stable_balance = 500;
+  contract.add_borrower_info_map(
+    &accounts(1).to_string(),
+    &BorrowerInfo {
+      interest_index: Index::one(),
+      reward_index: D128::zero(),
+      loan_amount: 1500,
+      pending_rewards: D128::zero(),
+    },
+  );
+
+  testing_env!(
+    context
+      .predecessor_account_id(accounts(0))
+      .current_account_id(accounts(0))
+      .build(),
+    VMConfig::default(),
+    RuntimeFeesConfig::default(),
+    Default::default(),
+    vec![PromiseResult::Failed]
+  );
+
+  contract.callback_borrow_transfer(
+    accounts(1).to_string(),
+    accounts(1).to_string(),
+    U128::from(1000u128),
+  );
+  assert_eq!(D128::new_exp(500, 0), contract.state.total_liabilities);
+  assert_eq!(1500, contract.state.stable_balance);
+  assert_eq!(
+    500,
+    contract
+      .get_borrower_info_map(&accounts(1).to_string())
+      .loan_amount
+  );
+}
+
+#[test]
+#[should_panic(expected = "borrow exceed limit")]
+fn borrow_against_current_liability() {
+  let (mut context, mut contract) = setup_contract();
+  contract.state.stable_balance = 10000;
+  contract.state.total_liabilities = D128::new_exp(1000, 0);
+  // a loan that landed while the borrow limit was queried
+  contract.add_borrower_info_map(
+    &accounts(1).to_string(),
+    &BorrowerInfo {
+      interest_index: Index::one(),
+      reward_index: D128::zero(),
+      loan_amount: 1000,
+      pending_rewards: D128::zero(),
+    },
+  );
+
+  testing_env!(
+    context
+      .predecessor_account_id(accounts(0))
+      .current_account_id(accounts(0))
+      .build(),
+    VMConfig::default(),
+    RuntimeFeesConfig::default(),
+    Default::default(),
+    vec![PromiseResult::Successful(
+      near_sdk::serde_json::to_vec(&(accounts(1).to_string(), U128::from(1500u128))).unwrap()
+    )]
+  );
+  contract.callback_borrow_stable(600, accounts(1).to_string());
+}
+
+#[test]
+fn repay_stable_on_behalf_of_borrower() {
+  let (mut context, mut contract) = setup_contract();
+  let borrower = AccountId::from("borrower");
+  contract.add_borrower_info_map(
+    &borrower,
+    &BorrowerInfo {
+      interest_index: Index::one(),
+      reward_index: D128::zero(),
+      loan_amount: 1000,
+      pending_rewards: D128::zero(),
+    },
+  );
+  contract.state.total_liabilities = D128::new_exp(1000, 0);
+
+  testing_env!(context
+    .predecessor_account_id(ValidAccountId::try_from("stable_coin").unwrap())
+    .build());
+
+  match contract.ft_on_transfer(
+    AccountId::from("vault"),
+    U128::from(1500u128),
+    String::from(r#"{"repay_stable":{"borrower":"borrower","repay_all":true}}"#),
+  ) {
+    PromiseOrValue::Value(refund_amount) => assert_eq!(U128::from(500u128), refund_amount),
+    PromiseOrValue::Promise(_) => panic!("unexpected promise"),
+  }
+
+  assert_eq!(0, contract.get_borrower_info_map(&borrower).loan_amount);
+  assert_eq!(D128::zero(), contract.state.total_liabilities);
+  assert_eq!(1000, contract.state.stable_balance);
+}
+
+#[test]
+#[should_panic(expected = "Repay amount 900 is less than the debt 1000")]
+fn repay_all_less_than_debt() {
+  let (mut context, mut contract) = setup_contract();
+  contract.add_borrower_info_map(
+    &accounts(1).to_string(),
+    &BorrowerInfo {
+      interest_index: Index::one(),
+      reward_index: D128::zero(),
+      loan_amount: 1000,
+      pending_rewards: D128::zero(),
+    },
+  );
+  contract.state.total_liabilities = D128::new_exp(1000, 0);
+
+  testing_env!(context
+    .predecessor_account_id(ValidAccountId::try_from("stable_coin").unwrap())
+    .build());
+
+  contract.ft_on_transfer(
+    accounts(1).to_string(),
+    U128::from(900u128),
+    String::from(r#"{"repay_stable":{"repay_all":true}}"#),
+  );
+}
+
+#[test]
+fn proper_delegated_borrow() {
+  let (mut context, mut contract) = setup_contract();
+  contract.config.max_borrow_factor = D128::one();
+  contract.state.stable_balance = 10000;
+  let liability = BorrowerInfo {
+    interest_index: Index::one(),
+    reward_index: D128::zero(),
+    loan_amount: 0,
+    pending_rewards: D128::zero(),
+  };
+  contract.add_borrower_info_map(&accounts(1).to_string(), &liability);
+
+  testing_env!(context
+    .predecessor_account_id(accounts(1))
+    .attached_deposit(10u128.pow(24))
+    .build());
+  contract.approve_delegate(accounts(2), U128::from(1000u128));
+
+  testing_env!(
+    context
+      .predecessor_account_id(accounts(0))
+      .current_account_id(accounts(0))
+      .attached_deposit(0)
+      .build(),
+    VMConfig::default(),
+    RuntimeFeesConfig::default(),
+    Default::default(),
+    vec![PromiseResult::Successful(
+      near_sdk::serde_json::to_vec(&(accounts(1).to_string(), U128::from(5000u128))).unwrap()
+    )]
+  );
+  contract.callback_borrow_stable(600, accounts(2).to_string());
+
+  assert_eq!(
+    600,
+    contract
+      .get_borrower_info_map(&accounts(1).to_string())
+      .loan_amount
+  );
+  assert_eq!(
+    U128::from(400u128),
+    contract.get_credit_allowance(accounts(1).to_string(), accounts(2).to_string())
+  );
+}
+
+#[test]
+#[should_panic(expected = "Borrow amount exceeds the credit allowance of charlie: 0")]
+fn borrow_on_behalf_without_allowance() {
+  let (mut context, mut contract) = setup_contract();
+  contract.add_borrower_info_map(
+    &accounts(1).to_string(),
+    &BorrowerInfo {
+      interest_index: Index::one(),
+      reward_index: D128::zero(),
+      loan_amount: 0,
+      pending_rewards: D128::zero(),
+    },
+  );
+
+  testing_env!(context.predecessor_account_id(accounts(2)).build());
+  contract.borrow_stable(100, Some(accounts(1).to_string()));
+}
@@ -1,6 +1,7 @@
 use near_sdk::test_utils::{accounts, VMContextBuilder};
-use near_sdk::{testing_env, MockedBlockchain};
+use near_sdk::{testing_env, MockedBlockchain, RuntimeFeesConfig, VMConfig};
 
+use crate::fungible_token_handler::FungibleTokenReceiver;
 use crate::*;
 
 pub fn setup_contract() -> (VMContextBuilder, Contract) {
@@ -13,7 +14,18 @@ pub fn setup_contract() -> (VMContextBuilder, Contract) {
     AccountId::from("owner"),
     D128::zero(),
     AccountId::from("stable_coin"),
+    FungibleTokenMetadata {
+      spec: FT_METADATA_SPEC.to_string(),
+      name: String::from("aUSD"),
+      symbol: String::from("aUSD"),
+      icon: None,
+      reference: None,
+      reference_hash: None,
+      decimals: 6,
+    },
     AccountId::from("overseer"),
+    AccountId::from("collector"),
+    AccountId::from("distributor"),
     D128::new_exp(1, -1),
     D128::new_exp(1, -1),
     D128::new_exp(100, 0),
@@ -26,23 +38,36 @@ pub fn setup_contract() -> (VMContextBuilder, Contract) {
 
 #[test]
 fn proper_compute_exchange_rate() {
-  let (context, mut contract) = setup_contract();
+  let (_, mut contract) = setup_contract();
   let mock_config = Config {
     owner_id: AccountId::from("owner"),
+    pending_owner_id: None,
+    guardian_id: AccountId::from("owner"),
     stable_coin_contract: AccountId::from("stable_coin"),
     max_borrow_factor: D128::one(),
     overseer_contract: AccountId::from("overseer"),
+    collector_contract: AccountId::from("collector"),
+    distributor_contract: AccountId::from("distributor"),
+    flash_loan_fee: D128::zero(),
+    global_borrow_cap: None,
+    account_borrow_cap: None,
   };
   let mock_state = State {
     total_liabilities: D128::new(50000u128 * 100_000_000),
     total_reserves: D128::new(550000u128 * 100_000_000),
-    last_interest_updated: context.context.block_index,
-    last_reward_updated: context.context.block_index,
-    global_interest_index: D128::one(),
+    last_interest_updated: current_block_time(),
+    last_reward_updated: current_block_time(),
+    global_interest_index: Index::one(),
     global_reward_index: D128::zero(),
     anc_emission_rate: D128::one(),
-    prev_stable_coin_total_supply: 0,
+    atoken_total_supply: 0,
     prev_exchange_rate: D128::one(),
+    target_deposit_rate: D128::one(),
+    stable_balance: 0,
+    flash_loan_due: 0,
+    flash_loan_repaid: 0,
+    epoch_snapshot_count: 0,
+    paused: PauseFlags::default(),
   };
   let mock_deposit_amount = Some(1000000u128);
 
@@ -51,3 +76,318 @@ fn proper_compute_exchange_rate() {
 
   // assert_eq!(exchange_rate, D128::new_exp(5, -1));
 }
+
+#[test]
+fn proper_epoch_state() {
+  let (mut context, mut contract) = setup_contract();
+  testing_env!(context.block_timestamp(100 * SECOND_TO_NANO).build());
+
+  let mock_state = State {
+    total_liabilities: D128::new_exp(1000000, 0),
+    total_reserves: D128::zero(),
+    last_interest_updated: 100,
+    last_reward_updated: 100,
+    global_interest_index: Index::one(),
+    global_reward_index: D128::zero(),
+    anc_emission_rate: D128::one(),
+    atoken_total_supply: 2000000u128,
+    prev_exchange_rate: D128::one(),
+    target_deposit_rate: D128::one(),
+    stable_balance: 1000000u128,
+    flash_loan_due: 0,
+    flash_loan_repaid: 0,
+    epoch_snapshot_count: 0,
+    paused: PauseFlags::default(),
+  };
+  contract.state = mock_state;
+
+  // (1000000 + 1000000 - 0) / 2000000
+  let epoch_state = contract.get_epoch_state(None, None);
+  assert_eq!(D128::one(), epoch_state.exchange_rate);
+  assert_eq!(U128::from(2000000u128), epoch_state.atoken_supply);
+  assert_eq!(D128::zero(), epoch_state.deposit_rate);
+
+  // borrow_rate = 0.1 + 0.5 * 0.1 = 0.15 a year
+  // exchange_rate = (1000000 + 1150000 - 0) / 2000000 = 1.075
+  let epoch_state = contract.get_epoch_state(Some(100 + SECONDS_PER_YEAR as u64), None);
+  assert_eq!(D128::new_exp(1075, -3), epoch_state.exchange_rate);
+  assert_eq!(D128::new_exp(75, -3), epoch_state.deposit_rate);
+  assert_eq!(D128::new_exp(1150000, 0), epoch_state.total_liabilities);
+  assert_eq!(D128::zero(), epoch_state.total_reserves);
+
+  // an hour accrues 1000000 * 0.15 * 3600 / 31536000 = 17.12328767
+  let epoch_state = contract.get_epoch_state(Some(3700), None);
+  assert_eq!(D128::new(1000017_12328767), epoch_state.total_liabilities);
+  assert_eq!(D128::new(1_00000856), epoch_state.exchange_rate);
+  // the 8 decimals of the exchange rate round the 7.5% down
+  assert_eq!(D128::new_exp(749856, -7), epoch_state.deposit_rate);
+
+  // view must not mutate state
+  assert_eq!(mock_state, contract.state);
+}
+
+#[test]
+fn epoch_state_of_empty_market() {
+  let (_, contract) = setup_contract();
+
+  // no aToken and no balance yet, the distributed interest is not in the ledger
+  let epoch_state = contract.get_epoch_state(Some(100), Some(U128::from(100u128)));
+  assert_eq!(D128::one(), epoch_state.exchange_rate);
+  assert_eq!(U128::from(0u128), epoch_state.atoken_supply);
+  assert_eq!(D128::zero(), epoch_state.deposit_rate);
+}
+
+#[test]
+#[should_panic(expected = "block_time must bigger than last_interest_updated")]
+fn epoch_state_before_last_interest_updated() {
+  let (_, mut contract) = setup_contract();
+  contract.state.last_interest_updated = 100;
+
+  contract.get_epoch_state(Some(99), None);
+}
+
+#[test]
+fn ft_on_transfer_from_unknown_token() {
+  let (mut context, mut contract) = setup_contract();
+  testing_env!(context
+    .predecessor_account_id(ValidAccountId::try_from("unknown").unwrap())
+    .build());
+
+  match contract.ft_on_transfer(
+    AccountId::from("depositor"),
+    U128::from(100u128),
+    String::from("\"deposit_stable\""),
+  ) {
+    PromiseOrValue::Value(refund_amount) => assert_eq!(U128::from(100u128), refund_amount),
+    PromiseOrValue::Promise(_) => panic!("unexpected promise"),
+  }
+}
+
+#[test]
+fn proper_distribute_interest() {
+  let (mut context, mut contract) = setup_contract();
+  contract.state.stable_balance = 1000;
+  testing_env!(context
+    .predecessor_account_id(ValidAccountId::try_from("stable_coin").unwrap())
+    .build());
+
+  // only the overseer can distribute interest
+  match contract.ft_on_transfer(
+    AccountId::from("depositor"),
+    U128::from(100u128),
+    String::from("\"distribute_interest\""),
+  ) {
+    PromiseOrValue::Value(refund_amount) => assert_eq!(U128::from(100u128), refund_amount),
+    PromiseOrValue::Promise(_) => panic!("unexpected promise"),
+  }
+  assert_eq!(1000, contract.state.stable_balance);
+
+  match contract.ft_on_transfer(
+    AccountId::from("overseer"),
+    U128::from(100u128),
+    String::from("\"distribute_interest\""),
+  ) {
+    PromiseOrValue::Value(refund_amount) => assert_eq!(U128::from(0u128), refund_amount),
+    PromiseOrValue::Promise(_) => panic!("unexpected promise"),
+  }
+  assert_eq!(1100, contract.state.stable_balance);
+  // no aToken is minted for it
+  assert_eq!(0, contract.state.atoken_total_supply);
+}
+
+#[test]
+#[should_panic(expected = "The account doesn't have enough balance")]
+fn redeem_stable_more_than_balance() {
+  let (mut context, mut contract) = setup_contract();
+  contract.internal_register_account(&accounts(1).to_string());
+  contract.internal_mint(&accounts(1).to_string(), 100);
+
+  testing_env!(context
+    .predecessor_account_id(accounts(1))
+    .attached_deposit(1)
+    .build());
+  contract.redeem_stable(U128::from(101u128));
+}
+
+#[test]
+fn proper_deposit_stable() {
+  let (mut context, mut contract) = setup_contract();
+  contract.internal_register_account(&accounts(1).to_string());
+  contract.internal_register_account(&accounts(2).to_string());
+  contract.internal_mint(&accounts(2).to_string(), 1000);
+  contract.state.stable_balance = 1000;
+
+  testing_env!(context
+    .predecessor_account_id(ValidAccountId::try_from("stable_coin").unwrap())
+    .build());
+
+  // exchange_rate = 1000 / 1000 = 1
+  contract.deposit_stable(accounts(1).to_string(), 500);
+  assert_eq!(U128::from(500u128), contract.ft_balance_of(accounts(1)));
+  assert_eq!(U128::from(1500u128), contract.ft_total_supply());
+  assert_eq!(1500, contract.state.stable_balance);
+}
+
+#[test]
+#[should_panic(expected = "is not registered")]
+fn deposit_stable_of_unregistered_depositor() {
+  let (mut context, mut contract) = setup_contract();
+  contract.state.stable_balance = 500;
+
+  testing_env!(context
+    .predecessor_account_id(ValidAccountId::try_from("stable_coin").unwrap())
+    .build());
+
+  contract.deposit_stable(accounts(1).to_string(), 1000);
+}
+
+#[test]
+fn proper_redeem_stable() {
+  let (mut context, mut contract) = setup_contract();
+  contract.internal_register_account(&accounts(1).to_string());
+  contract.internal_mint(&accounts(1).to_string(), 1000);
+  contract.state.stable_balance = 2000;
+
+  testing_env!(context
+    .predecessor_account_id(accounts(1))
+    .attached_deposit(1)
+    .build());
+
+  // exchange_rate = 2000 / 1000 = 2
+  contract.redeem_stable(U128::from(400u128));
+  assert_eq!(U128::from(600u128), contract.ft_balance_of(accounts(1)));
+  assert_eq!(U128::from(600u128), contract.ft_total_supply());
+  assert_eq!(1200, contract.state.stable_balance);
+}
+
+#[test]
+#[should_panic(expected = "No Stable Available")]
+fn redeem_stable_with_reserves_exceeding_balance() {
+  let (mut context, mut contract) = setup_contract();
+  contract.internal_register_account(&accounts(1).to_string());
+  contract.internal_mint(&accounts(1).to_string(), 1000);
+  contract.state.stable_balance = 1000;
+  contract.state.total_liabilities = D128::new_exp(1000, 0);
+  contract.state.total_reserves = D128::new_exp(500, 0);
+
+  testing_env!(context
+    .predecessor_account_id(accounts(1))
+    .attached_deposit(1)
+    .build());
+
+  // exchange_rate = (1000 + 1000 - 500) / 1000 = 1.5, only 500 is free of reserves
+  contract.redeem_stable(U128::from(400u128));
+}
+
+#[test]
+fn revert_redeem_on_failed_transfer() {
+  let (mut context, mut contract) = setup_contract();
+  contract.internal_register_account(&accounts(1).to_string());
+  contract.internal_mint(&accounts(1).to_string(), 500);
+  contract.state.stable_balance = 500;
+
+  testing_env!(
+    context
+      .predecessor_account_id(accounts(0))
+      .current_account_id(accounts(0))
+      .build(),
+    VMConfig::default(),
+    RuntimeFeesConfig::default(),
+    Default::default(),
+    vec![PromiseResult::Failed]
+  );
+
+  contract.callback_redeem_transfer(
+    accounts(1).to_string(),
+    U128::from(1000u128),
+    U128::from(1100u128),
+  );
+  assert_eq!(1600, contract.state.stable_balance);
+  assert_eq!(U128::from(1500u128), contract.ft_total_supply());
+  assert_eq!(U128::from(1500u128), contract.ft_balance_of(accounts(1)));
+}
+
+#[test]
+fn proper_atoken_transfer() {
+  let (mut context, mut contract) = setup_contract();
+  contract.internal_register_account(&accounts(1).to_string());
+  contract.internal_register_account(&accounts(2).to_string());
+  contract.internal_mint(&accounts(1).to_string(), 1000);
+
+  testing_env!(context
+    .predecessor_account_id(accounts(1))
+    .attached_deposit(1)
+    .build());
+  contract.ft_transfer(accounts(2), U128::from(300u128), None);
+
+  assert_eq!(U128::from(700u128), contract.ft_balance_of(accounts(1)));
+  assert_eq!(U128::from(300u128), contract.ft_balance_of(accounts(2)));
+  assert_eq!(U128::from(1000u128), contract.ft_total_supply());
+}
+
+#[test]
+fn proper_epoch_snapshot() {
+  let (mut context, mut contract) = setup_contract();
+  contract.state.stable_balance = 3000;
+  contract.state.total_liabilities = D128::new_exp(1000, 0);
+
+  testing_env!(context
+    .predecessor_account_id(ValidAccountId::try_from("overseer").unwrap())
+    .build());
+  contract.execute_epoch_operations(
+    D128::new_exp(1, -2),
+    D128::new_exp(2, -2),
+    D128::new_exp(1, -2),
+    U128::from(0u128),
+  );
+
+  let snapshots = contract.get_epoch_snapshots(None, None);
+  assert_eq!(1, snapshots.len());
+  assert_eq!(0, snapshots[0].epoch);
+  assert_eq!(D128::new_exp(1, -2), snapshots[0].deposit_rate);
+  // 1000 / (3000 + 1000)
+  assert_eq!(D128::new_exp(25, -2), snapshots[0].utilization_ratio);
+  // 0.1 + 0.25 * 0.1
+  assert_eq!(D128::new_exp(125, -3), snapshots[0].borrow_rate);
+  assert_eq!(D128::new_exp(1000, 0), snapshots[0].total_liabilities);
+}
+
+#[test]
+fn epoch_operations_of_empty_market() {
+  let (mut context, mut contract) = setup_contract();
+
+  // the distributed interest is not in the ledger yet
+  testing_env!(context
+    .predecessor_account_id(ValidAccountId::try_from("overseer").unwrap())
+    .build());
+  contract.execute_epoch_operations(
+    D128::zero(),
+    D128::new_exp(2, -2),
+    D128::new_exp(1, -2),
+    U128::from(100u128),
+  );
+  assert_eq!(D128::one(), contract.state.prev_exchange_rate);
+}
+
+#[test]
+fn epoch_snapshots_ring_buffer() {
+  let (_, mut contract) = setup_contract();
+
+  for block_time in 0..MAX_EPOCH_SNAPSHOTS + 5 {
+    contract.internal_record_epoch_snapshot(block_time, D128::zero());
+  }
+
+  // the first 5 epochs are overwritten
+  let snapshots = contract.get_epoch_snapshots(None, Some(2));
+  assert_eq!(
+    vec![5, 6],
+    snapshots.iter().map(|s| s.epoch).collect::<Vec<u64>>()
+  );
+
+  let snapshots = contract.get_epoch_snapshots(Some(MAX_EPOCH_SNAPSHOTS + 2), None);
+  assert_eq!(
+    vec![MAX_EPOCH_SNAPSHOTS + 3, MAX_EPOCH_SNAPSHOTS + 4],
+    snapshots.iter().map(|s| s.epoch).collect::<Vec<u64>>()
+  );
+  assert_eq!(MAX_EPOCH_SNAPSHOTS + 3, snapshots[0].block_time);
+}
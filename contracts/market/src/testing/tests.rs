@@ -1,5 +1,5 @@
 use near_sdk::test_utils::{accounts, VMContextBuilder};
-use near_sdk::{testing_env, MockedBlockchain};
+use near_sdk::{testing_env, MockedBlockchain, RuntimeFeesConfig, VMConfig};
 
 use crate::*;
 
@@ -13,7 +13,18 @@ pub fn setup_contract() -> (VMContextBuilder, Contract) {
     AccountId::from("owner"),
     D128::zero(),
     AccountId::from("stable_coin"),
+    FungibleTokenMetadata {
+      spec: FT_METADATA_SPEC.to_string(),
+      name: String::from("aUSD"),
+      symbol: String::from("aUSD"),
+      icon: None,
+      reference: None,
+      reference_hash: None,
+      decimals: 6,
+    },
     AccountId::from("overseer"),
+    AccountId::from("collector"),
+    AccountId::from("distributor"),
     D128::new_exp(1, -1),
     D128::new_exp(1, -1),
     D128::new_exp(100, 0),
@@ -34,11 +45,20 @@ fn proper_initialization() {
     AccountId::from("stable_coin"),
     contract.config.stable_coin_contract
   );
+  assert_eq!(String::from("aUSD"), contract.ft_metadata().symbol);
   assert_eq!(
     AccountId::from("overseer"),
     contract.config.overseer_contract
   );
-  assert_eq!(D128::zero(), contract.state.anc_emission_rate);
+  assert_eq!(
+    AccountId::from("collector"),
+    contract.config.collector_contract
+  );
+  assert_eq!(
+    AccountId::from("distributor"),
+    contract.config.distributor_contract
+  );
+  assert_eq!(D128::one(), contract.state.anc_emission_rate);
   assert_eq!(
     D128::new_exp(1, -1),
     contract.interest_model_config.base_rate
@@ -70,10 +90,12 @@ fn update_config() {
   let (_, mut contract) = setup_contract();
 
   contract.update_config(
-    None,
     Some(AccountId::from("stable_coin1")),
     Some(D128::one()),
     Some(AccountId::from("overseer1")),
+    Some(AccountId::from("collector1")),
+    Some(AccountId::from("distributor1")),
+    Some(D128::new_exp(9, -4)),
   );
 
   assert_eq!(D128::one(), contract.config.max_borrow_factor);
@@ -85,23 +107,98 @@ fn update_config() {
     AccountId::from("overseer1"),
     contract.config.overseer_contract
   );
+  assert_eq!(
+    AccountId::from("collector1"),
+    contract.config.collector_contract
+  );
+  assert_eq!(
+    AccountId::from("distributor1"),
+    contract.config.distributor_contract
+  );
+  assert_eq!(D128::new_exp(9, -4), contract.config.flash_loan_fee);
+}
 
-  contract.update_config(Some(AccountId::from("owner1")), None, None, None);
+#[test]
+fn proper_ownership_transfer() {
+  let (mut context, mut contract) = setup_contract();
+
+  contract.propose_owner(ValidAccountId::try_from("owner1").unwrap());
+  assert_eq!(AccountId::from("owner"), contract.config.owner_id);
+  assert_eq!(
+    Some(AccountId::from("owner1")),
+    contract.config.pending_owner_id
+  );
+
+  testing_env!(context
+    .predecessor_account_id(ValidAccountId::try_from("owner1").unwrap())
+    .attached_deposit(1)
+    .build());
+  contract.accept_ownership();
 
   assert_eq!(AccountId::from("owner1"), contract.config.owner_id);
+  assert_eq!(None, contract.config.pending_owner_id);
+}
+
+#[test]
+#[should_panic(expected = "Can only be called by the pending owner")]
+fn accept_cancelled_ownership() {
+  let (mut context, mut contract) = setup_contract();
+
+  contract.propose_owner(ValidAccountId::try_from("owner1").unwrap());
+  contract.cancel_owner_proposal();
+
+  testing_env!(context
+    .predecessor_account_id(ValidAccountId::try_from("owner1").unwrap())
+    .attached_deposit(1)
+    .build());
+  contract.accept_ownership();
 }
 
 #[test]
 #[should_panic(expected = "Can only be called by the owner")]
 fn assert_owner() {
+  let (mut context, mut contract) = setup_contract();
+
+  testing_env!(context
+    .predecessor_account_id(ValidAccountId::try_from("owner1").unwrap())
+    .attached_deposit(1)
+    .build());
+  contract.update_config(None, None, None, None, None, None);
+}
+
+#[test]
+fn update_interest_model_config() {
   let (_, mut contract) = setup_contract();
 
-  contract.update_config(Some(AccountId::from("owner1")), None, None, None);
-  contract.update_config(Some(AccountId::from("owner2")), None, None, None);
+  contract.update_interest_model_config(
+    Some(D128::new_exp(2, -1)),
+    None,
+    Some(D128::new_exp(8, -1)),
+    Some(D128::one()),
+  );
+
+  assert_eq!(
+    D128::new_exp(2, -1),
+    contract.interest_model_config.base_rate
+  );
+  assert_eq!(
+    D128::new_exp(1, -1),
+    contract.interest_model_config.interest_multiplier
+  );
+  assert_eq!(
+    D128::new_exp(8, -1),
+    contract.interest_model_config.optimal_utilization
+  );
+  assert_eq!(D128::one(), contract.interest_model_config.jump_multiplier);
 }
 
 #[test]
-fn update_interest_model_config() {}
+#[should_panic(expected = "optimal_utilization must be in (0, 1]")]
+fn invalid_optimal_utilization() {
+  let (_, mut contract) = setup_contract();
+
+  contract.update_interest_model_config(None, None, Some(D128::new_exp(11, -1)), None);
+}
 
 #[test]
 fn update_distribution_model_config() {}
@@ -121,6 +218,40 @@ fn proper_borrow_rate() {
   assert_eq!(D128::new_exp(1, -1), rate);
 }
 
+#[test]
+fn proper_borrow_rate_with_kink() {
+  let (_, mut contract) = setup_contract();
+
+  contract.update_interest_model_config(None, None, Some(D128::new_exp(8, -1)), Some(D128::one()));
+
+  // utilization 0.5, below kink
+  // 0.1 + 0.5 * 0.1
+  let rate = contract.get_borrow_rate(
+    1000000u128,
+    D128::new_exp(1000000, 0),
+    D128::zero(),
+  );
+  assert_eq!(D128::new_exp(15, -2), rate);
+
+  // utilization 0.8, at kink
+  // 0.1 + 0.8 * 0.1
+  let rate = contract.get_borrow_rate(
+    200000u128,
+    D128::new_exp(800000, 0),
+    D128::zero(),
+  );
+  assert_eq!(D128::new_exp(18, -2), rate);
+
+  // utilization 0.9, above kink
+  // 0.1 + 0.8 * 0.1 + 0.1 * 1.0
+  let rate = contract.get_borrow_rate(
+    100000u128,
+    D128::new_exp(900000, 0),
+    D128::zero(),
+  );
+  assert_eq!(D128::new_exp(28, -2), rate);
+}
+
 #[test]
 fn proper_emission_rate() {
   let (_, contract) = setup_contract();
@@ -172,3 +303,392 @@ fn proper_emission_rate() {
   );
   assert_eq!(D128::new_exp(10, 0), rate);
 }
+
+#[test]
+fn restore_reserves_on_failed_transfer() {
+  let (mut context, mut contract) = setup_contract();
+  contract.state.total_reserves = D128::zero();
+
+  testing_env!(
+    context
+      .predecessor_account_id(accounts(0))
+      .current_account_id(accounts(0))
+      .build(),
+    VMConfig::default(),
+    RuntimeFeesConfig::default(),
+    Default::default(),
+    vec![PromiseResult::Failed]
+  );
+
+  contract.callback_transfer_reserves(U128::from(1000u128));
+  assert_eq!(D128::new_exp(1000, 0), contract.state.total_reserves);
+}
+
+#[test]
+fn proper_storage_deposit() {
+  let (mut context, mut contract) = setup_contract();
+  contract.state.global_interest_index = Index::new(2 * INDEX_DECIMAL);
+
+  let min = contract.storage_balance_bounds().min;
+  assert!(min.0 > 0);
+  assert_eq!(None, contract.storage_balance_of(accounts(1)));
+
+  testing_env!(context
+    .predecessor_account_id(accounts(1))
+    .attached_deposit(min.0 + 100)
+    .build());
+  let storage_balance = contract.storage_deposit(None, None);
+  assert_eq!(min, storage_balance.total);
+  assert_eq!(U128(0), storage_balance.available);
+
+  assert_eq!(
+    BorrowerInfo {
+      interest_index: Index::new(2 * INDEX_DECIMAL),
+      reward_index: D128::zero(),
+      loan_amount: 0,
+      pending_rewards: D128::zero(),
+    },
+    contract.get_borrower_info_map(&accounts(1).to_string())
+  );
+}
+
+#[test]
+#[should_panic(expected = "The attached deposit is less than the minimum storage balance")]
+fn storage_deposit_below_minimum() {
+  let (mut context, mut contract) = setup_contract();
+  let min = contract.storage_balance_bounds().min;
+
+  testing_env!(context
+    .predecessor_account_id(accounts(1))
+    .attached_deposit(min.0 - 1)
+    .build());
+  contract.storage_deposit(None, None);
+}
+
+#[test]
+#[should_panic(expected = "Can't unregister the account with an open position")]
+fn storage_unregister_with_open_loan() {
+  let (mut context, mut contract) = setup_contract();
+  let min = contract.storage_balance_bounds().min;
+
+  testing_env!(context
+    .predecessor_account_id(accounts(1))
+    .attached_deposit(min.0)
+    .build());
+  contract.storage_deposit(None, None);
+
+  let mut borrower_info = contract.get_borrower_info_map(&accounts(1).to_string());
+  borrower_info.loan_amount = 100;
+  contract.add_borrower_info_map(&accounts(1).to_string(), &borrower_info);
+
+  testing_env!(context.attached_deposit(1).build());
+  contract.storage_unregister(None);
+}
+
+#[test]
+fn proper_flash_loan() {
+  let (mut context, mut contract) = setup_contract();
+  contract.config.flash_loan_fee = D128::new_exp(1, -2);
+  contract.state.stable_balance = 10000;
+  contract.add_flash_loan_receiver(accounts(2));
+
+  testing_env!(context
+    .predecessor_account_id(accounts(1))
+    .attached_deposit(1)
+    .build());
+  contract.flash_loan(accounts(2).to_string(), U128(1000), String::from(""));
+  assert_eq!(9000, contract.state.stable_balance);
+  assert_eq!(1010, contract.state.flash_loan_due);
+
+  // overpaid amount is refunded
+  assert_eq!(90, contract.repay_flash_loan(1100));
+  assert_eq!(10010, contract.state.stable_balance);
+
+  testing_env!(
+    context
+      .predecessor_account_id(accounts(0))
+      .current_account_id(accounts(0))
+      .build(),
+    VMConfig::default(),
+    RuntimeFeesConfig::default(),
+    Default::default(),
+    vec![PromiseResult::Successful(vec![])]
+  );
+  contract.callback_flash_loan(accounts(2).to_string(), U128(1000));
+  assert_eq!(D128::new_exp(10, 0), contract.state.total_reserves);
+  assert_eq!(0, contract.state.flash_loan_due);
+  assert_eq!(0, contract.state.flash_loan_repaid);
+}
+
+#[test]
+#[should_panic(expected = "was not repaid in full: 1000 of 1010")]
+fn flash_loan_not_repaid() {
+  let (mut context, mut contract) = setup_contract();
+  contract.state.stable_balance = 10000;
+  contract.config.flash_loan_fee = D128::new_exp(1, -2);
+  contract.add_flash_loan_receiver(accounts(2));
+
+  testing_env!(context
+    .predecessor_account_id(accounts(1))
+    .attached_deposit(1)
+    .build());
+  contract.flash_loan(accounts(2).to_string(), U128(1000), String::from(""));
+  contract.repay_flash_loan(1000);
+
+  testing_env!(
+    context
+      .predecessor_account_id(accounts(0))
+      .current_account_id(accounts(0))
+      .build(),
+    VMConfig::default(),
+    RuntimeFeesConfig::default(),
+    Default::default(),
+    vec![PromiseResult::Successful(vec![])]
+  );
+  contract.callback_flash_loan(accounts(2).to_string(), U128(1000));
+}
+
+#[test]
+#[should_panic(expected = "The flash loan receiver bob is not trusted")]
+fn flash_loan_to_untrusted_receiver() {
+  let (mut context, mut contract) = setup_contract();
+  contract.state.stable_balance = 10000;
+
+  testing_env!(context
+    .predecessor_account_id(accounts(1))
+    .attached_deposit(1)
+    .build());
+  contract.flash_loan(accounts(1).to_string(), U128(1000), String::from(""));
+}
+
+#[test]
+fn flash_loan_with_failed_transfer() {
+  let (mut context, mut contract) = setup_contract();
+  contract.state.stable_balance = 10000;
+  contract.add_flash_loan_receiver(accounts(2));
+
+  testing_env!(context
+    .predecessor_account_id(accounts(1))
+    .attached_deposit(1)
+    .build());
+  contract.flash_loan(accounts(2).to_string(), U128(1000), String::from(""));
+
+  // the receiver never got the loan, its hook is not called
+  testing_env!(
+    context
+      .predecessor_account_id(accounts(0))
+      .current_account_id(accounts(0))
+      .build(),
+    VMConfig::default(),
+    RuntimeFeesConfig::default(),
+    Default::default(),
+    vec![PromiseResult::Failed]
+  );
+  contract.callback_flash_loan_transfer(
+    accounts(2).to_string(),
+    accounts(1).to_string(),
+    U128(1000),
+    U128(0),
+    String::from(""),
+  );
+  assert_eq!(10000, contract.state.stable_balance);
+  assert_eq!(0, contract.state.flash_loan_due);
+}
+
+#[test]
+#[should_panic(expected = "Another flash loan is in progress")]
+fn flash_loan_in_progress() {
+  let (mut context, mut contract) = setup_contract();
+  contract.state.stable_balance = 10000;
+  contract.add_flash_loan_receiver(accounts(2));
+
+  testing_env!(context
+    .predecessor_account_id(accounts(1))
+    .attached_deposit(1)
+    .build());
+  contract.flash_loan(accounts(2).to_string(), U128(1000), String::from(""));
+  contract.flash_loan(accounts(2).to_string(), U128(1000), String::from(""));
+}
+
+#[test]
+fn proper_event_log() {
+  setup_contract();
+
+  Event::RepayStable {
+    borrower: &accounts(1).to_string(),
+    repay_amount: U128::from(100u128),
+  }
+  .emit();
+
+  assert_eq!(
+    near_sdk::test_utils::get_logs(),
+    vec![
+      r#"EVENT_JSON:{"standard":"money_market","version":"1.0.0","event":"repay_stable","data":{"borrower":"bob","repay_amount":"100"}}"#
+    ]
+  );
+}
+
+#[test]
+#[should_panic(expected = "Borrow is paused")]
+fn borrow_while_paused() {
+  let (mut context, mut contract) = setup_contract();
+  contract.set_guardian(accounts(1));
+
+  testing_env!(context
+    .predecessor_account_id(accounts(1))
+    .attached_deposit(1)
+    .build());
+  contract.pause(Operation::Borrow);
+  assert_eq!(true, contract.get_state().paused.borrow);
+  assert_eq!(false, contract.get_state().paused.deposit);
+
+  contract.borrow_stable(100, None);
+}
+
+#[test]
+#[should_panic(expected = "Can only be called by the owner")]
+fn unpause_by_guardian() {
+  let (mut context, mut contract) = setup_contract();
+  contract.set_guardian(accounts(1));
+
+  testing_env!(context
+    .predecessor_account_id(accounts(1))
+    .attached_deposit(1)
+    .build());
+  contract.pause(Operation::Deposit);
+  contract.unpause(Operation::Deposit);
+}
+
+#[test]
+fn unpause_by_owner() {
+  let (_, mut contract) = setup_contract();
+
+  contract.pause(Operation::Redeem);
+  assert_eq!(true, contract.get_state().paused.redeem);
+  contract.unpause(Operation::Redeem);
+  assert_eq!(PauseFlags::default(), contract.get_state().paused);
+}
+
+#[test]
+#[should_panic(expected = "Can only be called by the guardian or the owner")]
+fn pause_by_non_guardian() {
+  let (mut context, mut contract) = setup_contract();
+
+  testing_env!(context
+    .predecessor_account_id(accounts(2))
+    .attached_deposit(1)
+    .build());
+  contract.pause(Operation::Borrow);
+}
+
+#[test]
+fn migrate_from_deployed_market() {
+  let mut context = VMContextBuilder::new();
+  testing_env!(context
+    .block_index(1_000)
+    .block_timestamp(10_000 * SECOND_TO_NANO)
+    .build());
+
+  let mut borrower_info_map = LookupMap::new(StorageKey::BorrowerInfo);
+  borrower_info_map.insert(
+    &accounts(1).to_string(),
+    &migration::OldBorrowerInfo {
+      interest_index: D128::new_exp(2, 0),
+      reward_index: D128::zero(),
+      loan_amount: 100,
+      pending_rewards: D128::zero(),
+    },
+  );
+  env::state_write(&migration::OldContract {
+    config: migration::OldConfig {
+      owner_id: AccountId::from("owner"),
+      stable_coin_contract: AccountId::from("stable_coin"),
+      max_borrow_factor: D128::one(),
+      overseer_contract: AccountId::from("overseer"),
+    },
+    state: migration::OldState {
+      anc_emission_rate: D128::new_exp(2, 0),
+      total_liabilities: D128::new_exp(100, 0),
+      total_reserves: D128::zero(),
+      last_interest_updated: 900,
+      global_interest_index: D128::new_exp(2, 0),
+      prev_exchange_rate: D128::one(),
+      prev_stable_coin_total_supply: 0,
+      last_reward_updated: 950,
+      global_reward_index: D128::zero(),
+    },
+    collection: migration::OldCollection { borrower_info_map },
+    interest_model_config: migration::OldInterestModelConfig {
+      base_rate: D128::new_exp(1, -8),
+      interest_multiplier: D128::new_exp(2, -8),
+    },
+    distribution_model_config: DistributionModelConfig {
+      emission_cap: D128::new_exp(100, 0),
+      emission_floor: D128::new_exp(10, 0),
+      increment_multiplier: D128::new_exp(11, -1),
+      decrement_multiplier: D128::new_exp(9, -1),
+    },
+  });
+
+  let contract = Contract::migrate(
+    AccountId::from("collector"),
+    AccountId::from("distributor"),
+    FungibleTokenMetadata {
+      spec: FT_METADATA_SPEC.to_string(),
+      name: String::from("aUSD"),
+      symbol: String::from("aUSD"),
+      icon: None,
+      reference: None,
+      reference_hash: None,
+      decimals: 6,
+    },
+    vec![
+      (accounts(1).to_string(), U128::from(50)),
+      (accounts(2).to_string(), U128::from(70)),
+    ],
+    vec![accounts(1).to_string()],
+    U128::from(20),
+    D128::new_exp(2, 0),
+  );
+
+  // 100 blocks of 2 seconds before the migration
+  let state = contract.get_state();
+  assert_eq!(9_800, state.last_interest_updated);
+  assert_eq!(9_900, state.last_reward_updated);
+  assert_eq!(D128::one(), state.anc_emission_rate);
+  assert_eq!(Index::new(2 * INDEX_DECIMAL), state.global_interest_index);
+  assert_eq!(120, state.atoken_total_supply);
+  assert_eq!(20, state.stable_balance);
+
+  // 15_768_000 blocks a year
+  let interest_model_config = &contract.interest_model_config;
+  assert_eq!(D128::new_exp(15768, -5), interest_model_config.base_rate);
+  assert_eq!(
+    D128::new_exp(31536, -5),
+    interest_model_config.interest_multiplier
+  );
+  assert_eq!(D128::one(), interest_model_config.optimal_utilization);
+  assert_eq!(
+    D128::new_exp(50, 0),
+    contract.distribution_model_config.emission_cap
+  );
+
+  let borrower_info = contract.get_borrower_info_map(&accounts(1).to_string());
+  assert_eq!(Index::new(2 * INDEX_DECIMAL), borrower_info.interest_index);
+  assert_eq!(100, borrower_info.loan_amount);
+  assert_eq!(U128::from(50), contract.ft_balance_of(accounts(1)));
+
+  // the aToken holder without a loan is registered
+  assert!(contract.storage_balance_of(accounts(2)).is_some());
+  assert_eq!(U128::from(70), contract.ft_balance_of(accounts(2)));
+  assert_eq!(
+    vec![accounts(1).to_string(), accounts(2).to_string()],
+    contract
+      .get_borrower_infos(None, None, None)
+      .into_iter()
+      .map(|(borrower, _)| borrower)
+      .collect::<Vec<AccountId>>()
+  );
+  assert!(contract.config.pending_owner_id.is_none());
+  assert_ne!(0, contract.account_storage_usage);
+}
@@ -2,133 +2,129 @@ use crate::*;
 
 #[ext_contract(fungible_token)]
 pub trait FungibleToken {
-  fn ft_total_supply(&self) -> PromiseOrValue<U128>;
-
   fn ft_balance_of(&self, account_id: AccountId) -> PromiseOrValue<U128>;
 
   fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>) -> Promise;
-
-  fn mint(&mut self, account_id: AccountId, amount: Balance);
-
-  fn burn(&mut self, account_id: AccountId, amount: Balance);
 }
 
 #[ext_contract(ext_overseer)]
 pub trait OverseerContract {
-  fn get_borrow_limit(
-    &self,
-    borrower: AccountId,
-    block_time: Option<BlockHeight>,
-  ) -> PromiseOrValue<(AccountId, U128)>;
+  fn query_borrow_limit(&self, borrower: AccountId) -> PromiseOrValue<(AccountId, U128)>;
 
   fn get_target_deposit_rate(&self) -> PromiseOrValue<D128>;
 }
 
+#[ext_contract(ext_distributor)]
+pub trait DistributorContract {
+  fn spend(&mut self, recipient: AccountId, amount: U128);
+}
+
+#[ext_contract(ext_flash_loan_receiver)]
+pub trait FlashLoanReceiver {
+  fn on_flash_loan(&mut self, initiator: AccountId, amount: U128, fee: U128, msg: String);
+}
+
 #[ext_contract(ext_self)]
 pub trait Contract {
-  fn callback_compute_interset(
-    &mut self,
-    block_height: BlockHeight,
-    deposit_amount: Option<Balance>,
-  );
+  fn callback_borrow_stable(&mut self, borrow_amount: Balance, receiver: AccountId);
 
-  fn callback_borrow_stable(&self, borrow_amount: Balance, liability: BorrowerInfo);
+  fn callback_transfer_reserves(&mut self, amount: U128);
 
-  fn callback_compute_exchange_rate(
-    &self,
-    amount: Balance,
-    deposit_amount: Option<Balance>,
-  ) -> D128;
+  fn callback_claim_reward(&mut self, borrower: AccountId, claim_amount: U128);
 
-  fn callback_deposit_stable(&self, deposit_amount: Balance, depositor: AccountId);
+  fn callback_reconcile_stable_balance(&mut self);
 
-  fn callback_redeem_stable(&self, burn_amount: Balance, redeemer: AccountId);
+  fn callback_flash_loan_transfer(
+    &mut self,
+    receiver_id: AccountId,
+    initiator: AccountId,
+    amount: U128,
+    fee: U128,
+    msg: String,
+  );
+
+  fn callback_flash_loan(&mut self, receiver_id: AccountId, amount: U128);
+
+  fn callback_redeem_transfer(
+    &mut self,
+    redeemer: AccountId,
+    burn_amount: U128,
+    redeem_amount: U128,
+  );
 
-  fn callback_execute_epoch_operations(
-    &self,
-    deposit_rate: D128,
-    target_deposit_rate: D128,
-    threshold_deposit_rate: D128,
-    distributed_intereset: U128,
+  fn callback_borrow_transfer(
+    &mut self,
+    borrower: AccountId,
+    receiver: AccountId,
+    borrow_amount: U128,
   );
 }
 
 #[near_bindgen]
 impl Contract {
-  #[private]
-  pub fn callback_compute_interset(
-    &mut self,
-    block_height: BlockHeight,
-    deposit_amount: Option<Balance>,
-  ) {
-    assert_eq!(env::promise_results_count(), 2, "This is a callback method");
-
-    match env::promise_result(0) {
-      PromiseResult::NotReady => unreachable!(),
-      PromiseResult::Failed => {
-        // TODO
-      }
-      PromiseResult::Successful(result) => {
-        let stable_coin_total_supply = near_sdk::serde_json::from_slice::<U128>(&result).unwrap().0;
-        match env::promise_result(1) {
-          PromiseResult::NotReady => unreachable!(),
-          PromiseResult::Failed => {
-            // TODO
-          }
-          PromiseResult::Successful(result) => {
-            let balance = near_sdk::serde_json::from_slice::<U128>(&result).unwrap().0
-              - deposit_amount.unwrap_or(0);
-
-            let borrow_rate = self.get_borrow_rate(
-              balance,
-              self.state.total_liabilities,
-              self.state.total_reserves,
-            );
-
-            let target_deposit_rate = D128::one(); // TODO after overseer contract
-
-            self.compute_interest_raw(
-              block_height,
-              stable_coin_total_supply,
-              borrow_rate,
-              target_deposit_rate,
-            )
-          }
-        }
-      }
-    }
-  }
-
+  /// The liability is read here, so a repayment or borrow landing while the
+  /// limit was queried is not overwritten
   #[private]
   pub fn callback_borrow_stable(
     &mut self,
     borrow_amount: Balance,
-    liability: &mut BorrowerInfo,
+    receiver: AccountId,
   ) -> (AccountId, u128) {
     assert_eq!(env::promise_results_count(), 1, "This is a callback method");
 
     match env::promise_result(0) {
       PromiseResult::NotReady => unreachable!(),
       PromiseResult::Failed => {
-        env::panic("fail".as_bytes());
+        env::panic("Failed to fetch the borrow limit".as_bytes());
       }
       PromiseResult::Successful(result) => {
         let (borrower, borrow_limit_raw) =
           near_sdk::serde_json::from_slice::<(AccountId, U128)>(&result).unwrap();
         let borrow_limit = borrow_limit_raw.0;
 
+        let mut liability: BorrowerInfo = self.get_borrower_info_map(&borrower);
+
+        self.compute_interest(current_block_time());
+        compute_borrower_interest(&self.state, &mut liability);
+
+        compute_reward(&mut self.state, current_block_time());
+        compute_borrower_reward(&self.state, &mut liability);
+
         if borrow_limit < borrow_amount + liability.loan_amount {
           env::panic("borrow exceed limit".as_bytes()); // TODO
         }
 
-        let current_balance = env::account_balance();
+        let current_balance = self.state.stable_balance;
 
         self.assert_max_borrow_factor(current_balance, borrow_amount);
+        self.assert_borrow_cap(&liability, borrow_amount);
+        if receiver != borrower {
+          self.internal_use_credit_allowance(&borrower, &receiver, borrow_amount);
+        }
 
         liability.loan_amount += borrow_amount;
         self.state.total_liabilities = self.state.total_liabilities + borrow_amount;
+        self.state.stable_balance -= borrow_amount;
 
-        self.add_borrower_info_map(&borrower, liability);
+        self.add_borrower_info_map(&borrower, &liability);
+
+        // the loan is staged above and reverted if the transfer fails
+        fungible_token::ft_transfer(
+          receiver.clone(),
+          U128::from(borrow_amount),
+          None,
+          &self.config.stable_coin_contract,
+          1,
+          TRANSFER_CALL_GAS,
+        )
+        .then(ext_self::callback_borrow_transfer(
+          borrower.clone(),
+          receiver,
+          U128::from(borrow_amount),
+          &env::current_account_id(),
+          NO_DEPOSIT,
+          TRANSFER_CALL_GAS,
+        ));
 
         return (borrower, borrow_amount);
       }
@@ -136,100 +132,105 @@ impl Contract {
   }
 
   #[private]
-  pub fn callback_compute_exchange_rate(
-    &self,
-    amount: Balance,
-    deposit_amount: Option<Balance>,
-  ) -> D128 {
+  pub fn callback_redeem_transfer(
+    &mut self,
+    redeemer: AccountId,
+    burn_amount: U128,
+    redeem_amount: U128,
+  ) {
     assert_eq!(env::promise_results_count(), 1, "This is a callback method");
 
     match env::promise_result(0) {
       PromiseResult::NotReady => unreachable!(),
       PromiseResult::Failed => {
-        env::panic("fail".as_bytes());
+        self.state.stable_balance += redeem_amount.0;
+        self.internal_mint(&redeemer, burn_amount.0);
       }
-      PromiseResult::Successful(result) => {
-        let stable_coin_total_supply = near_sdk::serde_json::from_slice::<U128>(&result).unwrap();
-
-        let balance = amount - deposit_amount.unwrap_or(0);
-
-        self.compute_exchange_rate_raw(stable_coin_total_supply.into(), balance)
+      PromiseResult::Successful(_) => {
+        Event::RedeemStable {
+          redeemer: &redeemer,
+          burn_amount,
+          redeem_amount,
+        }
+        .emit();
       }
     }
   }
 
   #[private]
-  fn callback_deposit_stable(&mut self, deposit_amount: Balance, depositor: AccountId) {
+  pub fn callback_borrow_transfer(
+    &mut self,
+    borrower: AccountId,
+    receiver: AccountId,
+    borrow_amount: U128,
+  ) {
     assert_eq!(env::promise_results_count(), 1, "This is a callback method");
 
     match env::promise_result(0) {
       PromiseResult::NotReady => unreachable!(),
       PromiseResult::Failed => {
-        env::panic("fail".as_bytes());
-      }
-      PromiseResult::Successful(result) => {
-        let exchange_rate = near_sdk::serde_json::from_slice::<D128>(&result).unwrap();
-        let mint_amount: Balance = (deposit_amount / exchange_rate).as_u128();
+        let mut liability: BorrowerInfo = self.get_borrower_info_map(&borrower);
+        liability.loan_amount = liability.loan_amount.saturating_sub(borrow_amount.0);
+        self.add_borrower_info_map(&borrower, &liability);
 
-        self.state.prev_stable_coin_total_supply += mint_amount;
+        self.state.total_liabilities = self.state.total_liabilities - borrow_amount.0;
+        self.state.stable_balance += borrow_amount.0;
 
-        fungible_token::mint(
-          depositor,
-          mint_amount,
-          &self.config.stable_coin_contract,
-          NO_DEPOSIT,
-          SINGLE_CALL_GAS,
-        );
+        if receiver != borrower {
+          self.internal_restore_credit_allowance(&borrower, &receiver, borrow_amount.0);
+        }
+      }
+      PromiseResult::Successful(_) => {
+        Event::BorrowStable {
+          borrower: &borrower,
+          receiver: &receiver,
+          borrow_amount,
+        }
+        .emit();
       }
     }
   }
-
   #[private]
-  fn callback_redeem_stable(&mut self, burn_amount: Balance, redeemer: AccountId) {
+  pub fn callback_transfer_reserves(&mut self, amount: U128) {
     assert_eq!(env::promise_results_count(), 1, "This is a callback method");
 
     match env::promise_result(0) {
       PromiseResult::NotReady => unreachable!(),
       PromiseResult::Failed => {
-        env::panic("fail".as_bytes());
+        // transfer to the collector failed, keep the reserves in the market
+        self.state.total_reserves = self.state.total_reserves + amount.0;
+        self.state.stable_balance += amount.0;
       }
-      PromiseResult::Successful(result) => {
-        let exchange_rate = near_sdk::serde_json::from_slice::<D128>(&result).unwrap();
-        let redeem_amount = burn_amount * exchange_rate;
-
-        let current_balance = env::account_balance();
+      PromiseResult::Successful(_) => {}
+    }
+  }
 
-        self.assert_redeem_amount(current_balance, redeem_amount);
+  #[private]
+  pub fn callback_claim_reward(&mut self, borrower: AccountId, claim_amount: U128) {
+    assert_eq!(env::promise_results_count(), 1, "This is a callback method");
 
-        self.state.prev_stable_coin_total_supply =
-          self.state.prev_stable_coin_total_supply - burn_amount;
+    match env::promise_result(0) {
+      PromiseResult::NotReady => unreachable!(),
+      PromiseResult::Failed => {
+        env::log(format!("Failed to spend {} rewards to {}", claim_amount.0, borrower).as_bytes());
 
-        fungible_token::burn(
-          redeemer.clone(),
-          burn_amount,
-          &self.config.stable_coin_contract,
-          NO_DEPOSIT,
-          SINGLE_CALL_GAS,
-        )
-        .and(fungible_token::ft_transfer(
-          redeemer,
-          U128::from(redeem_amount.as_u128()),
-          None,
-          &self.config.stable_coin_contract,
-          NO_DEPOSIT,
-          SINGLE_CALL_GAS,
-        ));
+        // restore the rewards deducted by claim_reward
+        let mut liability: BorrowerInfo = self.get_borrower_info_map(&borrower);
+        liability.pending_rewards = liability.pending_rewards + claim_amount.0;
+        self.add_borrower_info_map(&borrower, &liability);
+      }
+      PromiseResult::Successful(_) => {
+        Event::ClaimReward {
+          borrower: &borrower,
+          claim_amount,
+        }
+        .emit();
       }
     }
   }
+
   #[private]
-  pub fn callback_execute_epoch_operations(
-    &mut self,
-    deposit_rate: D128,
-    target_deposit_rate: D128,
-    threshold_deposit_rate: D128,
-    distributed_intereset: U128,
-  ) {
+  pub fn callback_reconcile_stable_balance(&mut self) {
     assert_eq!(env::promise_results_count(), 1, "This is a callback method");
 
     match env::promise_result(0) {
@@ -238,42 +239,86 @@ impl Contract {
         env::panic("fail".as_bytes());
       }
       PromiseResult::Successful(result) => {
-        let stable_coin_total_supply = near_sdk::serde_json::from_slice::<U128>(&result).unwrap().0;
-        let balance: Balance = env::account_balance() - distributed_intereset.0;
-
-        let borrow_rate = self.get_borrow_rate(
-          balance,
-          self.state.total_liabilities,
-          self.state.total_reserves,
-        );
-        let block_height = env::block_index();
-
-        self.compute_interest_raw(
-          block_height,
-          stable_coin_total_supply,
-          borrow_rate,
-          target_deposit_rate,
+        let cur_balance: Balance = near_sdk::serde_json::from_slice::<U128>(&result).unwrap().0;
+
+        env::log(
+          format!(
+            "Reconcile stable balance: {} -> {}",
+            self.state.stable_balance, cur_balance
+          )
+          .as_bytes(),
         );
 
-        self.state.prev_exchange_rate = self
-          .compute_exchange_rate_raw(stable_coin_total_supply, balance + distributed_intereset.0);
+        self.state.stable_balance = cur_balance;
+        // closes a flash loan that was never repaid
+        self.state.flash_loan_due = 0;
+        self.state.flash_loan_repaid = 0;
+      }
+    }
+  }
 
-        self.compute_reward(block_height);
+  /// Calls the hook of the receiver once it holds the loan,
+  /// a failed transfer closes the loan without calling it
+  #[private]
+  pub fn callback_flash_loan_transfer(
+    &mut self,
+    receiver_id: AccountId,
+    initiator: AccountId,
+    amount: U128,
+    fee: U128,
+    msg: String,
+  ) {
+    assert_eq!(env::promise_results_count(), 1, "This is a callback method");
 
-        let total_reserves = self.state.total_reserves.as_u128();
+    match env::promise_result(0) {
+      PromiseResult::NotReady => unreachable!(),
+      PromiseResult::Failed => {
+        self.state.stable_balance += amount.0;
+        self.state.flash_loan_due = 0;
+        self.state.flash_loan_repaid = 0;
+      }
+      PromiseResult::Successful(_) => {
+        ext_flash_loan_receiver::on_flash_loan(
+          initiator,
+          amount,
+          fee,
+          msg,
+          &receiver_id,
+          NO_DEPOSIT,
+          SINGLE_CALL_GAS,
+        )
+        .then(ext_self::callback_flash_loan(
+          receiver_id,
+          amount,
+          &env::current_account_id(),
+          NO_DEPOSIT,
+          TRANSFER_CALL_GAS,
+        ));
+      }
+    }
+  }
 
-        if total_reserves != 0 && balance > total_reserves {
-          self.state.total_reserves = self.state.total_reserves - total_reserves;
-          // TODO
-        }
+  /// Settles the flash loan in progress, the fee goes to reserves.
+  /// A shortfall fails the flow and leaves the loan open, which blocks further
+  /// flash loans until the owner reconciles the stable balance.
+  #[private]
+  pub fn callback_flash_loan(&mut self, receiver_id: AccountId, amount: U128) {
+    assert_eq!(env::promise_results_count(), 1, "This is a callback method");
 
-        self.state.anc_emission_rate = self.get_emission_rate(
-          deposit_rate,
-          target_deposit_rate,
-          threshold_deposit_rate,
-          self.state.anc_emission_rate,
-        );
-      }
+    let repaid: Balance = self.state.flash_loan_repaid;
+    if repaid < self.state.flash_loan_due {
+      env::panic(
+        format!(
+          "Flash loan to {} was not repaid in full: {} of {}",
+          receiver_id, repaid, self.state.flash_loan_due
+        )
+        .as_bytes(),
+      );
     }
+
+    self.state.total_reserves = self.state.total_reserves + D128::new_exp(repaid - amount.0, 0);
+
+    self.state.flash_loan_due = 0;
+    self.state.flash_loan_repaid = 0;
   }
 }
@@ -1,5 +1,8 @@
 use crate::*;
 
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
 #[near_bindgen]
 impl Contract {
   pub fn get_config(&self) -> Config {
@@ -10,75 +13,208 @@ impl Contract {
     self.state
   }
 
-  // TODO Is is right?
+  pub fn is_flash_loan_receiver(&self, receiver_id: AccountId) -> bool {
+    self.collection.flash_loan_receivers.contains(&receiver_id)
+  }
+
+  /// Stable coin balance of the market
   pub fn get_balance(&self) -> Balance {
-    env::account_balance()
+    self.state.stable_balance
   }
 
-  // TODO: Is it view method? Because it should use ext_contract method...
+  /// Returns the epoch state projected to `block_time` without mutating the contract state.
+  /// `distributed_interest` is excluded from the market balance, as it is not a deposit.
   pub fn get_epoch_state(
-    &mut self,
-    block_height: Option<BlockHeight>,
-    distributed_intereset: Option<D128>,
-  ) {
-    let distributed_intereset = distributed_intereset.unwrap_or(D128::zero());
-    // let stable_coin_total_supply =
-    let balance = env::account_balance();
-
-    if let Some(block_height) = block_height {
-      if block_height < self.state.last_interest_updated {
-        env::panic("block_height must bigger than last_interest_updated".as_bytes());
+    &self,
+    block_time: Option<u64>,
+    distributed_interest: Option<U128>,
+  ) -> EpochStateResponse {
+    let mut state: State = self.state;
+
+    let distributed_interest: Balance = distributed_interest.map(|v| v.0).unwrap_or(0);
+    let atoken_supply: u128 = state.atoken_total_supply;
+    // an empty market has nothing to exclude
+    let balance: Balance = state.stable_balance.saturating_sub(distributed_interest);
+
+    let mut deposit_rate: D128 = D128::zero();
+
+    let exchange_rate: D128 = if let Some(block_time) = block_time {
+      if block_time < state.last_interest_updated {
+        env::panic("block_time must bigger than last_interest_updated".as_bytes());
       }
 
-      let borrow_rate = self.get_borrow_rate(
+      let passed_time: u64 = block_time - state.last_interest_updated;
+      let prev_exchange_rate: D128 = state.prev_exchange_rate;
+      let target_deposit_rate: D128 = state.target_deposit_rate;
+
+      let borrow_rate =
+        self.get_borrow_rate(balance, state.total_liabilities, state.total_reserves);
+
+      compute_interest_raw(
+        &mut state,
+        block_time,
         balance,
-        self.state.total_liabilities,
-        self.state.total_reserves,
+        atoken_supply,
+        borrow_rate,
+        target_deposit_rate,
       );
 
-      // ext_overseer::get_target_deposit_rate(
-      //   &self.config.overseer_contract,
-      //   NO_DEPOSIT,
-      //   SINGLE_CALL_GAS,
-      // ).then(
-      //   // ext_self::callback_get_epoch_state
-      // )
+      // compute_interest_raw stores the current exchange rate as prev_exchange_rate
+      deposit_rate = D128::yearly_growth(prev_exchange_rate, state.prev_exchange_rate, passed_time);
+
+      state.prev_exchange_rate
+    } else {
+      compute_exchange_rate_raw(&state, atoken_supply, balance)
+    };
+
+    EpochStateResponse {
+      exchange_rate,
+      atoken_supply: U128::from(atoken_supply),
+      deposit_rate,
+      total_liabilities: state.total_liabilities,
+      total_reserves: state.total_reserves,
     }
   }
 
   pub fn get_borrower_info(
-    &mut self,
+    &self,
     borrower: AccountId,
-    block_height: Option<BlockHeight>,
+    block_time: Option<u64>,
   ) -> BorrowerInfo {
-    let mut borrwer_info: BorrowerInfo = self.get_borrower_info_map(&borrower);
+    let block_time = block_time.unwrap_or_else(current_block_time);
+    let state: State = self.compute_state_at(block_time);
 
-    let block_height = if let Some(block_height) = block_height {
-      block_height
-    } else {
-      env::block_index()
+    let mut borrower_info: BorrowerInfo = self.get_borrower_info_map(&borrower);
+    compute_borrower_interest(&state, &mut borrower_info);
+    compute_borrower_reward(&state, &mut borrower_info);
+
+    borrower_info
+  }
+
+  /// Debt of `borrower` at `block_time`, zero for an account that never registered.
+  /// Summed by the overseer over every market it manages.
+  pub fn get_loan_amount(&self, borrower: AccountId, block_time: Option<u64>) -> U128 {
+    if self.collection.borrower_info_map.get(&borrower).is_none() {
+      return U128::from(0);
+    }
+
+    U128::from(self.get_borrower_info(borrower, block_time).loan_amount)
+  }
+
+  pub fn get_claimable_rewards(
+    &self,
+    borrower: AccountId,
+    block_time: Option<u64>,
+  ) -> U128 {
+    let borrower_info: BorrowerInfo = self.get_borrower_info(borrower, block_time);
+
+    U128::from(borrower_info.pending_rewards.as_u128())
+  }
+
+  /// Returns how much more can be borrowed under the caps at `block_time`.
+  /// `account` is only set when `borrower` is given.
+  pub fn get_borrow_capacity(
+    &self,
+    borrower: Option<AccountId>,
+    block_time: Option<u64>,
+  ) -> BorrowCapacityResponse {
+    let block_time = block_time.unwrap_or_else(current_block_time);
+    let state: State = self.compute_state_at(block_time);
+
+    let global = self.config.global_borrow_cap.map(|cap| {
+      let total_liabilities: Balance = state.total_liabilities.as_u128();
+      U128::from(cap.saturating_sub(total_liabilities))
+    });
+
+    let account = match (self.config.account_borrow_cap, borrower) {
+      (Some(cap), Some(borrower)) => {
+        let mut borrower_info: BorrowerInfo = self.get_borrower_info_map(&borrower);
+        compute_borrower_interest(&state, &mut borrower_info);
+        Some(U128::from(cap.saturating_sub(borrower_info.loan_amount)))
+      }
+      _ => None,
+    };
+
+    BorrowCapacityResponse { global, account }
+  }
+
+  /// Returns the retained epoch snapshots after epoch `start_after` in ascending order,
+  /// starting from the oldest one. Only the last `MAX_EPOCH_SNAPSHOTS` epochs are kept.
+  pub fn get_epoch_snapshots(
+    &self,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+  ) -> Vec<EpochSnapshot> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as u64;
+
+    let count: u64 = self.state.epoch_snapshot_count;
+    let oldest: u64 = count.saturating_sub(MAX_EPOCH_SNAPSHOTS);
+    let start: u64 = match start_after {
+      Some(start_after) => std::cmp::max(start_after.saturating_add(1), oldest),
+      None => oldest,
     };
+    let end: u64 = std::cmp::min(start.saturating_add(limit), count);
 
-    self.compute_interest(block_height, None);
-    self.compute_borrower_interest(&mut borrwer_info);
+    (start..end)
+      .map(|epoch| {
+        self
+          .collection
+          .epoch_snapshots
+          .get(epoch % MAX_EPOCH_SNAPSHOTS)
+          .unwrap()
+      })
+      .collect()
+  }
 
-    self.compute_reward(block_height);
-    self.compute_borrower_reward(&mut borrwer_info);
+  /// Returns borrower infos ordered by account, after the `start_after` account.
+  /// Borrowers removed between two pages don't shift the cursor.
+  pub fn get_borrower_infos(
+    &self,
+    start_after: Option<AccountId>,
+    limit: Option<u32>,
+    block_time: Option<u64>,
+  ) -> Vec<(AccountId, BorrowerInfo)> {
+    let block_time = block_time.unwrap_or_else(current_block_time);
+    let state: State = self.compute_state_at(block_time);
 
-    borrwer_info
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let borrower_infos: Box<dyn Iterator<Item = (AccountId, BorrowerInfo)>> = match start_after {
+      Some(start_after) => Box::new(self.collection.borrower_info_map.iter_from(start_after)),
+      None => Box::new(self.collection.borrower_info_map.iter()),
+    };
+
+    // only the requested page is read from storage
+    borrower_infos
+      .take(limit)
+      .map(|(borrower, mut borrower_info)| {
+        compute_borrower_interest(&state, &mut borrower_info);
+        compute_borrower_reward(&state, &mut borrower_info);
+        (borrower, borrower_info)
+      })
+      .collect()
   }
+}
 
-  // pub fn get_borrower_infos(
-  //   &mut self,
-  //   start_after: Option<AccountId>,
-  //   limit: Option<u32>,
-  // ) -> Vec<BorrowerInfo> {
-  //   let start_after = if let Some(start_after) = start_after {
-  //     Some(start_after)
-  //   } else {
-  //     None
-  //   };
-
-  //   let borrower_infos: Vec<BorrowerInfo> =
-  // }
+impl Contract {
+  /// Projects interest and reward indexes to `block_time` on a copy of the state.
+  pub(crate) fn compute_state_at(&self, block_time: u64) -> State {
+    let mut state: State = self.state;
+
+    let balance: Balance = state.stable_balance;
+    let atoken_supply: u128 = state.atoken_total_supply;
+    let target_deposit_rate: D128 = state.target_deposit_rate;
+    let borrow_rate = self.get_borrow_rate(balance, state.total_liabilities, state.total_reserves);
+
+    compute_interest_raw(
+      &mut state,
+      block_time,
+      balance,
+      atoken_supply,
+      borrow_rate,
+      target_deposit_rate,
+    );
+    compute_reward(&mut state, block_time);
+
+    state
+  }
 }
@@ -2,13 +2,24 @@ use crate::*;
 
 #[near_bindgen]
 impl Contract {
-  pub fn lock_collateral(&mut self, collaterals: Tokens) {
-    let borrower = env::predecessor_account_id();
+  /// Locks collateral of `borrower` (the caller by default), an approved delegate
+  /// can lock on behalf of the owner
+  pub fn lock_collateral(&mut self, collaterals: Tokens, borrower: Option<AccountId>) {
+    self.assert_not_paused(Operation::Lock);
+    let caller = env::predecessor_account_id();
+    let borrower = borrower.unwrap_or_else(|| caller.clone());
+    self.assert_delegate(&borrower, &caller);
     let mut cur_collaterals: Tokens = self.get_collateral_map(&borrower);
 
-    cur_collaterals.add(collaterals);
+    cur_collaterals.add(collaterals.clone());
     self.add_collateral_map(&borrower, &cur_collaterals);
 
+    Event::LockCollateral {
+      borrower: &borrower,
+      collaterals: &collaterals,
+    }
+    .emit();
+
     for collateral in cur_collaterals {
       let white_list_elem: WhitelistElem = self.get_white_list_elem_map(&collateral.0);
       ext_custody_bnear::lock_collateral(
@@ -22,77 +33,176 @@ impl Contract {
   }
 
   pub fn unlock_collateral(&mut self, collaterals: Tokens) {
+    self.assert_not_paused(Operation::Unlock);
     let borrower = env::predecessor_account_id();
     let mut cur_collaterals: Tokens = self.get_collateral_map(&borrower);
 
     cur_collaterals.sub(collaterals.clone());
 
-    let block_height = env::block_index();
-
-    let (borrow_limit, _) = self.compute_borrow_limit(&collaterals, Some(env::block_timestamp()));
-
-    ext_market::get_borrower_info(
-      borrower.clone(),
-      Some(block_height),
-      &self.config.market_contract,
-      NO_DEPOSIT,
-      SINGLE_CALL_GAS,
-    )
-    .then(ext_self::callback_unlock_collateral(
-      borrower,
-      cur_collaterals,
-      borrow_limit,
-      block_height,
-      &env::current_account_id(),
-      NO_DEPOSIT,
-      SINGLE_CALL_GAS,
-    ));
+    let block_time = env::block_timestamp() / SECOND_TO_NANO;
+
+    // the remaining collateral has to cover the loans in all markets
+    let (borrow_limit, _) = self.compute_borrow_limit(&cur_collaterals, Some(block_time));
+
+    self
+      .internal_query_loan_amounts(&borrower, block_time, None)
+      .unwrap_or_else(|| env::panic(b"No market is registered"))
+      .then(ext_self::callback_unlock_collateral(
+        borrower,
+        collaterals,
+        cur_collaterals,
+        borrow_limit,
+        block_time,
+        &env::current_account_id(),
+        NO_DEPOSIT,
+        SINGLE_CALL_GAS,
+      ));
   }
 
-  pub fn liquidate_collateral(&self, borrower: AccountId) {
-    let mut cur_collaterals: Tokens = self.get_collateral_map(&borrower);
+  /// Liquidates an unsafe loan, the seized collateral is sold in the liquidation
+  /// queue and the proceeds repay the loan in `market_contract`.
+  /// The caller is the liquidator and receives the liquidator fee.
+  pub fn liquidate_collateral(&self, borrower: AccountId, market_contract: AccountId) {
+    self.assert_not_paused(Operation::Liquidate);
+    if self.collection.market_map.get(&market_contract).is_none() {
+      env::panic(format!("The market {} is not registered", market_contract).as_bytes());
+    }
+    let cur_collaterals: Tokens = self.get_collateral_map(&borrower);
+
+    let block_time = env::block_timestamp() / SECOND_TO_NANO;
 
     let (borrow_limit, collateral_prices) =
-      self.compute_borrow_limit(&cur_collaterals, Some(env::block_timestamp()));
-
-    let block_height = env::block_index();
-
-    ext_market::get_borrower_info(
-      borrower.clone(),
-      Some(block_height),
-      &self.config.market_contract,
-      NO_DEPOSIT,
-      SINGLE_CALL_GAS,
-    )
-    .then(ext_self::callback_liquidate_collateral(
-      borrower,
-      cur_collaterals,
-      borrow_limit,
-      block_height,
-      &env::current_account_id(),
-      NO_DEPOSIT,
-      SINGLE_CALL_GAS,
-    ));
+      self.compute_borrow_limit(&cur_collaterals, Some(block_time));
+
+    // the callback forwards the rest of the gas to the custody contracts
+    let query_gas: Gas = QUERY_GAS * self.collection.market_map.len();
+    let callback_gas: Gas = env::prepaid_gas() - env::used_gas() - query_gas - GAS_RESERVE;
+
+    self
+      .internal_query_loan_amounts(&borrower, block_time, None)
+      .unwrap_or_else(|| env::panic(b"No market is registered"))
+      .then(ext_self::callback_liquidate_collateral(
+        borrower,
+        env::predecessor_account_id(),
+        market_contract,
+        cur_collaterals,
+        borrow_limit,
+        collateral_prices,
+        &env::current_account_id(),
+        NO_DEPOSIT,
+        callback_gas,
+      ));
+  }
+
+  /// Collateral to seize so that the loan drops back to `safe_ratio` of the borrow limit,
+  /// the same share of every collateral. `liquidation_discount` of the seized value
+  /// is expected to go to bid premiums and fees. The proceeds repay the loan in a
+  /// single market, so no more than `market_loan_amount` is seized.
+  pub(crate) fn compute_liquidation_amount(
+    &self,
+    borrow_amount: Balance,
+    market_loan_amount: Balance,
+    borrow_limit: Balance,
+    collaterals: &Tokens,
+    collateral_prices: &[(AccountId, D128)],
+  ) -> Tokens {
+    let collateral_value: D128 = collaterals
+      .iter()
+      .zip(collateral_prices.iter())
+      .fold(D128::zero(), |value, (collateral, (_, price))| {
+        value + collateral.1 * *price
+      });
+
+    let safe_borrow: D128 = D128::new_exp(borrow_limit, 0) * self.config.safe_ratio;
+    let expected_repay: D128 = collateral_value * (D128::one() - self.config.liquidation_discount);
+
+    // after seizing a share r: B - r * V' = safe_ratio * (1 - r) * L,
+    // so r = (B - safe_ratio * L) / (V' - safe_ratio * L)
+    let liquidation_ratio: D128 = if expected_repay <= D128::new_exp(borrow_amount, 0) {
+      // everything is seized, even that won't repay the whole loan
+      D128::one()
+    } else {
+      (D128::new_exp(borrow_amount, 0) - safe_borrow) / (expected_repay - safe_borrow)
+    };
+    let liquidation_ratio: D128 = if expected_repay > D128::new_exp(market_loan_amount, 0) {
+      std::cmp::min(
+        liquidation_ratio,
+        D128::new_exp(market_loan_amount, 0) / expected_repay,
+      )
+    } else {
+      liquidation_ratio
+    };
+
+    collaterals
+      .iter()
+      .map(|collateral| {
+        (
+          collateral.0.clone(),
+          liquidation_ratio.mul_int(collateral.1),
+        )
+      })
+      .filter(|collateral| collateral.1 > 0)
+      .collect()
+  }
+
+  /// Borrow limit of `borrower` net of the loans in the other registered markets,
+  /// queried by a market before it lends
+  pub fn query_borrow_limit(&self, borrower: AccountId) -> PromiseOrValue<(AccountId, U128)> {
+    let collaterals = self.get_collateral_map(&borrower);
+    let cur_time = env::block_timestamp() / SECOND_TO_NANO;
+    // a market always lends with fresh prices, checked against the current block
+    let (borrow_limit, _) = self.compute_borrow_limit(&collaterals, Some(cur_time));
+
+    let caller = env::predecessor_account_id();
+
+    match self.internal_query_loan_amounts(&borrower, cur_time, Some(&caller)) {
+      Some(promise) => promise
+        .then(ext_self::callback_borrow_limit(
+          borrower,
+          U128::from(borrow_limit),
+          &env::current_account_id(),
+          NO_DEPOSIT,
+          QUERY_GAS,
+        ))
+        .into(),
+      None => PromiseOrValue::Value((borrower, U128::from(borrow_limit))),
+    }
   }
 
+  /// Returns the borrow limit with the oracle price of each collateral token,
+  /// a token without a price panics. With `block_time` in seconds,
+  /// a price older than the price timeframe panics too.
   pub(crate) fn compute_borrow_limit(
     &self,
     collaterals: &Tokens,
-    block_time: Option<BlockHeight>, // TODO: What is it?
-  ) -> (u128, Vec<D128>) {
+    block_time: Option<u64>,
+  ) -> (u128, Vec<(AccountId, D128)>) {
     let mut borrow_limit: u128 = 0;
-    let mut collateral_prices: Vec<D128> = vec![];
+    let mut collateral_prices: Vec<(AccountId, D128)> = vec![];
 
     for collateral in collaterals.iter() {
       let collateral_token = collateral.0.clone();
       let collateral_amount = collateral.1;
 
-      let price = self.state.last_price_response.price;
+      let price_response: PriceResponse = self.get_price(&collateral_token);
+      if let Some(block_time) = block_time {
+        let price_age: u64 = block_time.saturating_sub(price_response.last_updated_at);
+        if price_age > self.config.price_timeframe {
+          env::panic(
+            format!(
+              "The price of {} is too old: updated {} seconds ago, the price timeframe is {} seconds",
+              collateral_token, price_age, self.config.price_timeframe
+            )
+            .as_bytes(),
+          );
+        }
+      }
+      let price = price_response.price;
 
       let elem: WhitelistElem = self.get_white_list_elem_map(&collateral.0);
       let collateral_value = collateral_amount * price;
       borrow_limit += (collateral_value * elem.max_ltv).as_u128();
-      collateral_prices.push(price);
+      collateral_prices.push((collateral_token, price));
     }
 
     (borrow_limit, collateral_prices)
@@ -0,0 +1,75 @@
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+  /// Lets `delegate_id` lock the caller's collateral, so it can back credit
+  /// delegated in the market. Unlocking stays with the owner.
+  #[payable]
+  pub fn approve_delegate(&mut self, delegate_id: ValidAccountId) {
+    if env::attached_deposit() == 0 {
+      env::panic(b"Requires attached deposit of at least 1 yoctoNEAR");
+    }
+    let initial_storage_usage = env::storage_usage();
+    let owner_id = env::predecessor_account_id();
+    let delegate_id: AccountId = delegate_id.into();
+
+    if owner_id == delegate_id {
+      env::panic(b"Can't delegate to yourself");
+    }
+    // only registered accounts can delegate
+    self.get_collateral_map(&owner_id);
+
+    self
+      .collection
+      .delegate_set
+      .insert(&(owner_id.clone(), delegate_id.clone()));
+    self.internal_settle_storage(initial_storage_usage);
+
+    Event::ApproveDelegate {
+      owner_id: &owner_id,
+      delegate_id: &delegate_id,
+    }
+    .emit();
+  }
+
+  /// Removes the approval and refunds its storage
+  #[payable]
+  pub fn revoke_delegate(&mut self, delegate_id: ValidAccountId) {
+    assert_one_yocto();
+    let initial_storage_usage = env::storage_usage();
+    let owner_id = env::predecessor_account_id();
+    let delegate_id: AccountId = delegate_id.into();
+
+    self
+      .collection
+      .delegate_set
+      .remove(&(owner_id.clone(), delegate_id.clone()));
+    self.internal_settle_storage(initial_storage_usage);
+
+    Event::RevokeDelegate {
+      owner_id: &owner_id,
+      delegate_id: &delegate_id,
+    }
+    .emit();
+  }
+
+  pub fn is_delegate(&self, owner_id: AccountId, delegate_id: AccountId) -> bool {
+    self
+      .collection
+      .delegate_set
+      .contains(&(owner_id, delegate_id))
+  }
+}
+
+impl Contract {
+  pub(crate) fn assert_delegate(&self, owner_id: &AccountId, delegate_id: &AccountId) {
+    if owner_id != delegate_id
+      && !self
+        .collection
+        .delegate_set
+        .contains(&(owner_id.clone(), delegate_id.clone()))
+    {
+      env::panic(format!("{} is not a delegate of {}", delegate_id, owner_id).as_bytes());
+    }
+  }
+}
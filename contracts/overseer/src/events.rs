@@ -0,0 +1,88 @@
+use crate::*;
+
+const EVENT_STANDARD: &str = "money_market";
+const EVENT_STANDARD_VERSION: &str = "1.0.0";
+
+/// NEP-297 events of the overseer, logged as `EVENT_JSON:{..}`
+#[derive(Serialize)]
+#[serde(
+  crate = "near_sdk::serde",
+  tag = "event",
+  content = "data",
+  rename_all = "snake_case"
+)]
+pub(crate) enum Event<'a> {
+  LockCollateral {
+    borrower: &'a AccountId,
+    collaterals: &'a Tokens,
+  },
+  UnlockCollateral {
+    borrower: &'a AccountId,
+    collaterals: &'a Tokens,
+  },
+  LiquidateCollateral {
+    borrower: &'a AccountId,
+    collaterals: &'a Tokens,
+  },
+  RegisterWhitelist {
+    collateral_token: &'a AccountId,
+    whitelist_elem: &'a WhitelistElem,
+  },
+  UpdateWhitelist {
+    collateral_token: &'a AccountId,
+    whitelist_elem: &'a WhitelistElem,
+  },
+  RestartPriceRequests {
+    collateral_token: &'a AccountId,
+  },
+  RegisterMarket {
+    market_contract: &'a AccountId,
+    market_elem: &'a MarketElem,
+  },
+  ReceiveCollateralRewards {
+    custody_contract: &'a AccountId,
+    stable_coin_contract: &'a AccountId,
+    amount: U128,
+  },
+  ExecuteEpochOperations {
+    market_contract: &'a AccountId,
+    epoch_state: &'a EpochState,
+    distributed_interest: U128,
+  },
+  ApproveDelegate {
+    owner_id: &'a AccountId,
+    delegate_id: &'a AccountId,
+  },
+  RevokeDelegate {
+    owner_id: &'a AccountId,
+    delegate_id: &'a AccountId,
+  },
+  Pause {
+    operation: Operation,
+  },
+  Unpause {
+    operation: Operation,
+  },
+  UpdateConfig(&'a Config),
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<'a> {
+  standard: &'static str,
+  version: &'static str,
+  #[serde(flatten)]
+  event: &'a Event<'a>,
+}
+
+impl Event<'_> {
+  pub(crate) fn emit(&self) {
+    let log = EventLog {
+      standard: EVENT_STANDARD,
+      version: EVENT_STANDARD_VERSION,
+      event: self,
+    };
+    let json = near_sdk::serde_json::to_string(&log).unwrap();
+    env::log(format!("EVENT_JSON:{}", json).as_bytes());
+  }
+}
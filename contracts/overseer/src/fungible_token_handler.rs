@@ -0,0 +1,70 @@
+use crate::*;
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum TokenReceiverMsg {
+  /// stable coin: rewards of the collateral held by a custody, they fund the interest buffer
+  CollateralRewards,
+}
+
+pub trait FungibleTokenReceiver {
+  fn ft_on_transfer(
+    &mut self,
+    sender_id: AccountId,
+    amount: U128,
+    msg: String,
+  ) -> PromiseOrValue<U128>;
+}
+
+#[near_bindgen]
+impl FungibleTokenReceiver for Contract {
+  /// Returns the amount of tokens to refund to `sender_id`
+  fn ft_on_transfer(
+    &mut self,
+    sender_id: AccountId,
+    amount: U128,
+    msg: String,
+  ) -> PromiseOrValue<U128> {
+    let token = env::predecessor_account_id();
+
+    let payload: TokenReceiverMsg = near_sdk::serde_json::from_str(&msg)
+      .expect("Failed to parse the payload, invalid `msg` format");
+
+    match payload {
+      TokenReceiverMsg::CollateralRewards => {
+        let is_stable_coin = self
+          .collection
+          .market_map
+          .values()
+          .any(|market_elem| market_elem.stable_coin_contract == token);
+        let is_custody = self
+          .collection
+          .white_list_elem_map
+          .values()
+          .any(|white_list_elem| white_list_elem.custody_contract == sender_id);
+        if !is_stable_coin || !is_custody {
+          env::log(b"Only custodies can send the stable coin of a market");
+
+          return PromiseOrValue::Value(amount);
+        }
+
+        let mut custody_rewards: CustodyRewards = self.get_custody_rewards(sender_id.clone());
+        custody_rewards.total_rewards += amount.0;
+        custody_rewards.last_received_at = env::block_timestamp();
+        self
+          .collection
+          .custody_rewards_map
+          .insert(&sender_id, &custody_rewards);
+
+        Event::ReceiveCollateralRewards {
+          custody_contract: &sender_id,
+          stable_coin_contract: &token,
+          amount,
+        }
+        .emit();
+
+        PromiseOrValue::Value(U128(0))
+      }
+    }
+  }
+}
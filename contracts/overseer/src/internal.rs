@@ -10,13 +10,194 @@ impl Contract {
     );
   }
 
-  pub(crate) fn internal_create_new_price_request(&self) {
+  pub(crate) fn assert_epoch_passed(&self, market_contract: &AccountId, block_time: u64) {
+    let epoch_state: EpochState = self
+      .collection
+      .epoch_state_map
+      .get(market_contract)
+      .unwrap();
+    if block_time < epoch_state.last_executed_time + self.config.epoch_period {
+      env::panic(b"The epoch period has not passed");
+    }
+  }
+
+  /// Queries the loan of `borrower` in every registered market but `exclude`,
+  /// `None` when there is no market to query
+  pub(crate) fn internal_query_loan_amounts(
+    &self,
+    borrower: &AccountId,
+    block_time: u64,
+    exclude: Option<&AccountId>,
+  ) -> Option<Promise> {
+    self
+      .collection
+      .market_map
+      .keys()
+      .filter(|market_contract| exclude != Some(market_contract))
+      .map(|market_contract| {
+        ext_market::get_loan_amount(
+          borrower.clone(),
+          Some(block_time),
+          &market_contract,
+          NO_DEPOSIT,
+          QUERY_GAS,
+        )
+      })
+      .reduce(|promise, next| promise.and(next))
+  }
+
+  /// Sums the loans returned by `internal_query_loan_amounts`.
+  /// Stable coins of all markets are valued 1:1.
+  pub(crate) fn internal_total_loan_amount(&self) -> Balance {
+    (0..env::promise_results_count())
+      .map(internal_loan_amount)
+      .sum()
+  }
+
+  /// Loan in `market_contract` among the ones returned by `internal_query_loan_amounts`
+  /// for all markets, the results follow the order of the market map
+  pub(crate) fn internal_market_loan_amount(&self, market_contract: &AccountId) -> Balance {
+    let index: usize = self
+      .collection
+      .market_map
+      .keys()
+      .position(|market| &market == market_contract)
+      .unwrap_or_else(|| {
+        env::panic(format!("The market {} is not registered", market_contract).as_bytes())
+      });
+    internal_loan_amount(index as u64)
+  }
+
+  /// Pays the requester for an oracle request on the USD price of `collateral_token`
+  pub(crate) fn internal_create_new_price_request(&mut self, collateral_token: &AccountId) {
+    let price_source: PriceSource = self.get_white_list_elem_map(collateral_token).price_source;
+    let payload = near_sdk::serde_json::json!({
+      "sources": [{
+        "end_point": price_source.end_point,
+        "source_path": price_source.source_path,
+      }],
+      // the token is tagged, so a response can be matched to the request
+      "tags": ["pricing", collateral_token],
+      "challenge_period": "120000000000",
+      "settlement_time": "1",
+      "data_type": { "Number": DECIMAL.to_string() },
+      "creator": env::current_account_id(),
+    });
+
+    self.state.price_request_token = Some(collateral_token.clone());
+    self.state.price_requested_at = env::block_timestamp() / SECOND_TO_NANO;
     fungible_token_transfer_call(
-        self.config.oracle_payment_token.clone(), 
-        self.config.requester_contract.clone(), 
-        1_000_000_000_000_000_000_000_000, 
-        // query NEAR price
-        format!("{{\"sources\": [{{ \"end_point\": \"https://api.coingecko.com/api/v3/simple/price?ids=tether%2Cnear&vs_currencies=usd\", \"source_path\":\"near.usd\"}}], \"tags\":[\"pricing\",\"near\"],  \"challenge_period\":\"120000000000\", \"settlement_time\":\"1\", \"data_type\":{{\"Number\":\"{}\"}}, \"creator\":\"{}\"}}", DECIMAL, env::current_account_id())
+      self.config.oracle_payment_token.clone(),
+      self.config.requester_contract.clone(),
+      self.config.oracle_payment_amount,
+      payload.to_string(),
     );
+  }
+
+  /// Whitelisted token after `collateral_token`, wrapping around to the first one
+  pub(crate) fn internal_next_price_token(&self, collateral_token: &AccountId) -> AccountId {
+    let tokens = self.collection.white_list_elem_map.keys_as_vector();
+    let index: u64 = (0..tokens.len())
+      .find(|index| &tokens.get(*index).unwrap() == collateral_token)
+      .map(|index| (index + 1) % tokens.len())
+      .unwrap_or(0);
+
+    tokens.get(index).unwrap()
+  }
+
+  /// Interest the buffer distributes to a market whose deposit rate is below
+  /// `threshold_deposit_rate`, at most `buffer_distribution_factor` of the buffer
+  pub(crate) fn compute_distributed_interest(
+    &self,
+    deposit_rate: D128,
+    prev_deposits: Balance,
+    passed_time: u64,
+    interest_buffer: Balance,
+  ) -> Balance {
+    if deposit_rate >= self.config.threshold_deposit_rate {
+      return 0;
+    }
+
+    let missing_deposit_rate: D128 = self.config.threshold_deposit_rate - deposit_rate;
+    let missing_deposits: Balance = missing_deposit_rate
+      .interest_over(D128::new_exp(prev_deposits, 0), passed_time)
+      .as_u128();
+    let distribution_buffer: Balance = self
+      .config
+      .buffer_distribution_factor
+      .mul_int(interest_buffer);
+
+    std::cmp::min(missing_deposits, distribution_buffer)
+  }
+
+  /// Stores the epoch state of `market_contract` with `distributed_interest` in the
+  /// exchange rate, then lets the market run its epoch operations
+  pub(crate) fn internal_update_epoch_state(
+    &mut self,
+    market_contract: AccountId,
+    passed_time: u64,
+    exchange_rate: D128,
+    atoken_supply: Balance,
+    interest_buffer: Balance,
+    distributed_interest: Balance,
+  ) -> Promise {
+    let mut epoch_state: EpochState = self
+      .collection
+      .epoch_state_map
+      .get(&market_contract)
+      .unwrap();
+
+    // the distributed interest goes to the depositors without minting aToken
+    let exchange_rate: D128 = if atoken_supply > 0 {
+      exchange_rate + D128::ratio(distributed_interest, atoken_supply)
+    } else {
+      exchange_rate
+    };
+    let deposit_rate: D128 =
+      compute_deposit_rate(epoch_state.prev_exchange_rate, exchange_rate, passed_time);
+
+    epoch_state.deposit_rate = deposit_rate;
+    epoch_state.prev_exchange_rate = exchange_rate;
+    epoch_state.prev_interest_buffer = interest_buffer - distributed_interest;
+    self
+      .collection
+      .epoch_state_map
+      .insert(&market_contract, &epoch_state);
+
+    Event::ExecuteEpochOperations {
+      market_contract: &market_contract,
+      epoch_state: &epoch_state,
+      distributed_interest: U128::from(distributed_interest),
+    }
+    .emit();
+
+    ext_market::execute_epoch_operations(
+      deposit_rate,
+      self.config.target_deposit_rate,
+      self.config.threshold_deposit_rate,
+      U128::from(distributed_interest),
+      &market_contract,
+      NO_DEPOSIT,
+      EPOCH_OPERATIONS_GAS,
+    )
+  }
+}
+
+/// Loan returned by the market query at `index`
+fn internal_loan_amount(index: u64) -> Balance {
+  match env::promise_result(index) {
+    PromiseResult::Successful(result) => {
+      near_sdk::serde_json::from_slice::<U128>(&result).unwrap().0
+    }
+    _ => env::panic(b"Failed to fetch the loan amount"),
+  }
 }
+
+/// Yearly deposit rate realized between two exchange rates
+pub(crate) fn compute_deposit_rate(
+  prev_exchange_rate: D128,
+  exchange_rate: D128,
+  passed_time: u64,
+) -> D128 {
+  D128::yearly_growth(prev_exchange_rate, exchange_rate, passed_time)
 }
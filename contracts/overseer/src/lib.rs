@@ -1,26 +1,38 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::LookupMap;
-use near_sdk::json_types::U128;
+use near_sdk::collections::{LookupMap, LookupSet, UnorderedMap};
+use near_sdk::json_types::{ValidAccountId, U128};
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::{
-    assert_one_yocto, env, ext_contract, near_bindgen, AccountId, Balance, BlockHeight,
-    BorshStorageKey, Gas, PanicOnDefault, Promise, PromiseOrValue, PromiseResult,
+    assert_one_yocto, env, ext_contract, near_bindgen, AccountId, Balance, BorshStorageKey, Gas,
+    PanicOnDefault, Promise, PromiseOrValue, PromiseResult, StorageUsage,
 };
 
 use uint::construct_uint;
 
-use crate::math::{D128, DECIMAL};
-use crate::state::{Collection, Config, State, WhitelistElem};
+use crate::events::Event;
+use crate::math::{D128, DECIMAL, SECONDS_PER_YEAR};
+use crate::pause::{Operation, PauseFlags};
+use crate::state::{
+    Collection, Config, CustodyRewards, EpochState, MarketElem, PriceSource, State, WhitelistElem,
+};
 use crate::tokens::{Token, Tokens, TokensMath};
 use crate::utils::{
-    ext_custody_bnear, ext_market, ext_self, fungible_token, fungible_token_transfer_call,
+    ext_custody_bnear, ext_market, ext_requester, ext_self, fungible_token,
+    fungible_token_transfer_call,
 };
 
+mod admin;
 mod collateral;
+mod delegation;
+mod events;
+mod fungible_token_handler;
 mod internal;
 mod math;
+mod migration;
 mod owner;
+mod pause;
 mod state;
+mod storage;
 #[cfg(test)]
 mod testing;
 mod tokens;
@@ -31,6 +43,44 @@ const NO_DEPOSIT: Balance = 0;
 
 const SINGLE_CALL_GAS: Gas = 100_000_000_000_000;
 
+// per market, a market is queried for the loan of a borrower
+const QUERY_GAS: Gas = 10_000_000_000_000;
+
+const LIQUIDATION_RESULT_GAS: Gas = 10_000_000_000_000;
+
+// kept for the rest of a call that forwards its remaining gas
+const GAS_RESERVE: Gas = 10_000_000_000_000;
+
+// market runs its epoch operations and may transfer its reserves to the collector
+const EPOCH_OPERATIONS_GAS: Gas = 70_000_000_000_000;
+
+// stable coin transfers the interest buffer to the market with a transfer call
+const DISTRIBUTE_INTEREST_GAS: Gas = 50_000_000_000_000;
+
+const DISTRIBUTE_RESULT_GAS: Gas = EPOCH_OPERATIONS_GAS + GAS_RESERVE;
+
+const EPOCH_CALLBACK_GAS: Gas = DISTRIBUTE_INTEREST_GAS + DISTRIBUTE_RESULT_GAS + GAS_RESERVE;
+
+// custody claims its rewards and sends them to the overseer
+const DISTRIBUTE_REWARDS_GAS: Gas = 90_000_000_000_000;
+
+// burnt by an epoch operation itself, besides the gas it attaches
+const EPOCH_OPERATIONS_OVERHEAD: Gas = 40_000_000_000_000;
+
+// burnt by an epoch operation for each custody it calls
+const CUSTODY_CALL_OVERHEAD: Gas = 5_000_000_000_000;
+
+// hourly epochs
+const DEFAULT_EPOCH_PERIOD: u64 = 3_600;
+
+// a rotation of a few price requests
+const DEFAULT_PRICE_TIMEFRAME: u64 = 900;
+
+// paid to the requester for every price request, in the oracle payment token
+const DEFAULT_ORACLE_PAYMENT_AMOUNT: Balance = 1_000_000_000_000_000_000_000_000;
+
+const SECOND_TO_NANO: u64 = 1_000_000_000;
+
 construct_uint! {
     /// 256-bit unsigned integer.
     pub struct U256(4);
@@ -38,23 +88,41 @@ construct_uint! {
 
 #[derive(BorshStorageKey, BorshSerialize)]
 pub(crate) enum StorageKey {
+    // the first two prefixes are the ones of the deployed overseer
     WhitelistElem,
     Collateral,
+    Price,
+    Delegate,
+    Market,
+    EpochState,
+    CustodyRewards,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(crate = "near_sdk::serde")]
 pub struct PriceResponse {
     pub price: D128,
+    // block time in seconds of the price request
     pub last_updated_at: u64,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EpochStateResponse {
+    pub exchange_rate: D128,
+    pub atoken_supply: U128,
+    pub deposit_rate: D128,
+    pub total_liabilities: D128,
+    pub total_reserves: D128,
+}
+
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct Contract {
     config: Config,
     state: State,
     collection: Collection,
+    account_storage_usage: StorageUsage,
 }
 
 #[near_bindgen]
@@ -63,7 +131,6 @@ impl Contract {
     pub fn new(
         owner_id: AccountId,
         oracle_contrract: AccountId,
-        market_contract: AccountId,
         liquidation_contract: AccountId,
         collector_contract: AccountId,
         target_deposit_rate: D128,
@@ -77,35 +144,48 @@ impl Contract {
         );
 
         let config = Config {
+            guardian_id: owner_id.clone(),
             owner_id,
+            pending_owner_id: None,
             oracle_contrract,
-            market_contract,
             liquidation_contract,
             collector_contract,
+            epoch_period: DEFAULT_EPOCH_PERIOD,
+            threshold_deposit_rate: target_deposit_rate,
             target_deposit_rate,
+            safe_ratio: D128::new_exp(8, -1),
+            liquidation_discount: D128::new_exp(1, -1),
+            buffer_distribution_factor: D128::new_exp(1, -1),
+            price_timeframe: DEFAULT_PRICE_TIMEFRAME,
             oracle_payment_token,
+            oracle_payment_amount: DEFAULT_ORACLE_PAYMENT_AMOUNT,
             requester_contract,
         };
 
         let state = State {
-            last_price_response: PriceResponse {
-                price: D128::one(),
-                last_updated_at: env::block_timestamp(),
-            },
+            price_request_token: None,
+            price_requested_at: 0,
+            paused: PauseFlags::default(),
         };
 
         let collection = Collection {
-            white_list_elem_map: LookupMap::new(StorageKey::WhitelistElem),
+            white_list_elem_map: UnorderedMap::new(StorageKey::WhitelistElem),
+            price_map: LookupMap::new(StorageKey::Price),
             collateral_map: LookupMap::new(StorageKey::Collateral),
+            delegate_set: LookupSet::new(StorageKey::Delegate),
+            market_map: UnorderedMap::new(StorageKey::Market),
+            epoch_state_map: LookupMap::new(StorageKey::EpochState),
+            custody_rewards_map: LookupMap::new(StorageKey::CustodyRewards),
         };
 
         let mut instance = Self {
             config,
             state,
             collection,
+            account_storage_usage: 0,
         };
 
-        instance.internal_create_new_price_request();
+        instance.measure_account_storage_usage();
 
         instance
     }
@@ -118,17 +198,67 @@ impl Contract {
         collateral_token: AccountId,
         custody_contract: AccountId,
         max_ltv: D128,
+        price_source: PriceSource,
     ) {
         assert_one_yocto();
-        self.add_white_list_elem_map(
-            &collateral_token,
-            &WhitelistElem {
-                name: name.to_string(),
-                symbol: symbol.to_string(),
-                custody_contract,
-                max_ltv,
+        self.assert_owner();
+        let white_list_elem = WhitelistElem {
+            name: name.to_string(),
+            symbol: symbol.to_string(),
+            custody_contract,
+            max_ltv,
+            price_source,
+        };
+        self.add_white_list_elem_map(&collateral_token, &white_list_elem);
+
+        // the first collateral starts the price requests, the rest join the rotation
+        if self.state.price_request_token.is_none() {
+            self.internal_create_new_price_request(&collateral_token);
+        }
+
+        Event::RegisterWhitelist {
+            collateral_token: &collateral_token,
+            whitelist_elem: &white_list_elem,
+        }
+        .emit();
+    }
+
+    /// Registers a stable coin market, the collateral backs the debt across all markets
+    #[payable]
+    pub fn register_market(
+        &mut self,
+        name: String,
+        symbol: String,
+        market_contract: AccountId,
+        stable_coin_contract: AccountId,
+    ) {
+        assert_one_yocto();
+        self.assert_owner();
+        if self.collection.market_map.get(&market_contract).is_some() {
+            env::panic(format!("The market {} is already registered", market_contract).as_bytes());
+        }
+
+        let market_elem = MarketElem {
+            name,
+            symbol,
+            stable_coin_contract,
+        };
+        self.collection.market_map.insert(&market_contract, &market_elem);
+        self.collection.epoch_state_map.insert(
+            &market_contract,
+            &EpochState {
+                deposit_rate: D128::zero(),
+                prev_exchange_rate: D128::one(),
+                prev_interest_buffer: 0,
+                last_executed_time: env::block_timestamp() / SECOND_TO_NANO,
             },
         );
+
+        Event::RegisterMarket {
+            market_contract: &market_contract,
+            market_elem: &market_elem,
+        }
+        .emit();
     }
 
     #[payable]
@@ -137,8 +267,10 @@ impl Contract {
         collateral_token: AccountId,
         custody_contract: Option<AccountId>,
         max_ltv: Option<D128>,
+        price_source: Option<PriceSource>,
     ) {
         assert_one_yocto();
+        self.assert_owner();
         let mut white_list_elem: WhitelistElem = self.get_white_list_elem_map(&collateral_token);
 
         if let Some(custody_contract) = custody_contract {
@@ -149,29 +281,99 @@ impl Contract {
             white_list_elem.max_ltv = max_ltv;
         }
 
+        if let Some(price_source) = price_source {
+            white_list_elem.price_source = price_source;
+        }
+
         self.add_white_list_elem_map(&collateral_token, &white_list_elem);
+
+        Event::UpdateWhitelist {
+            collateral_token: &collateral_token,
+            whitelist_elem: &white_list_elem,
+        }
+        .emit();
     }
 
-    pub fn execute_epoch_operations(
-        &mut self,
-        deposit_rate: D128,
-        target_deposit_rate: D128,
-        threshold_deposit_rate: D128,
-        distributed_intereset: U128,
-    ) {
-        // ext_stable_coin::ft_total_supply(
-        //     &self.config.stable_coin_contract,
-        //     NO_DEPOSIT,
-        //     SINGLE_CALL_GAS,
-        // )
-        // .then(ext_self::callback_execute_epoch_operations(
-        //     deposit_rate,
-        //     target_deposit_rate,
-        //     threshold_deposit_rate,
-        //     distributed_intereset,
-        //     &env::current_account_id(),
-        //     NO_DEPOSIT,
-        //     SINGLE_CALL_GAS,
-        // ));
+    /// Fetches the oracle request in flight, anyone can call it
+    pub fn update_price(&mut self) -> Promise {
+        let collateral_token: AccountId = match &self.state.price_request_token {
+            Some(collateral_token) => collateral_token.clone(),
+            None => env::panic(b"No price request in progress"),
+        };
+
+        ext_requester::get_data_request(
+            env::current_account_id(),
+            &self.config.requester_contract,
+            NO_DEPOSIT,
+            QUERY_GAS,
+        )
+        .then(ext_self::callback_get_price_response(
+            collateral_token,
+            &env::current_account_id(),
+            NO_DEPOSIT,
+            SINGLE_CALL_GAS,
+        ))
+    }
+
+    /// Runs the epoch operations of `market_contract` once its epoch period has passed,
+    /// the interest buffer tops up a deposit rate below `threshold_deposit_rate`.
+    /// The custodies distribute their rewards to the buffer of the next epoch.
+    /// Anyone can call it.
+    pub fn execute_epoch_operations(&mut self, market_contract: AccountId) -> Promise {
+        let market_elem: MarketElem = match self.collection.market_map.get(&market_contract) {
+            Some(market_elem) => market_elem,
+            None => {
+                env::panic(format!("The market {} is not registered", market_contract).as_bytes())
+            }
+        };
+        let block_time = env::block_timestamp() / SECOND_TO_NANO;
+        self.assert_epoch_passed(&market_contract, block_time);
+
+        // custodies get the gas left by the epoch operations, one at a time,
+        // the ones left out wait for an epoch with more gas
+        let mut custody_gas: Gas = (env::prepaid_gas() - env::used_gas())
+            .saturating_sub(2 * QUERY_GAS + EPOCH_CALLBACK_GAS + EPOCH_OPERATIONS_OVERHEAD);
+        let custody_cost: Gas = DISTRIBUTE_REWARDS_GAS + CUSTODY_CALL_OVERHEAD;
+        for white_list_elem in self.collection.white_list_elem_map.values() {
+            custody_gas = match custody_gas.checked_sub(custody_cost) {
+                Some(custody_gas) => custody_gas,
+                None => {
+                    env::log(
+                        format!(
+                            "Not enough gas to distribute the rewards of {}",
+                            white_list_elem.custody_contract
+                        )
+                        .as_bytes(),
+                    );
+                    continue;
+                }
+            };
+            ext_custody_bnear::distribute_rewards(
+                &white_list_elem.custody_contract,
+                NO_DEPOSIT,
+                DISTRIBUTE_REWARDS_GAS,
+            );
+        }
+
+        fungible_token::ft_balance_of(
+            env::current_account_id(),
+            &market_elem.stable_coin_contract,
+            NO_DEPOSIT,
+            QUERY_GAS,
+        )
+        .and(ext_market::get_epoch_state(
+            Some(block_time),
+            None,
+            &market_contract,
+            NO_DEPOSIT,
+            QUERY_GAS,
+        ))
+        .then(ext_self::callback_execute_epoch_operations(
+            market_contract,
+            block_time,
+            &env::current_account_id(),
+            NO_DEPOSIT,
+            EPOCH_CALLBACK_GAS,
+        ))
     }
 }
@@ -12,6 +12,7 @@ construct_uint! {
 }
 
 pub const DECIMAL: u128 = 100_000_000; //1e8
+pub const SECONDS_PER_YEAR: u128 = 31_536_000;
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Copy, Clone, Debug)]
 #[serde(crate = "near_sdk::serde")]
@@ -72,6 +73,31 @@ impl D128 {
     pub fn as_u128(self) -> u128 {
         self.num.0 / DECIMAL
     }
+
+    /// Returns the interest on `amount` at this yearly rate over `seconds`,
+    /// scaled in one step so short periods do not round to zero
+    pub fn interest_over(self, amount: D128, seconds: u64) -> D128 {
+        let num: u128 = (U256::from(amount.num.0) * U256::from(self.num.0) * U256::from(seconds)
+            / U256::from(DECIMAL * SECONDS_PER_YEAR))
+        .as_u128();
+
+        Self::new(num)
+    }
+
+    /// Returns the yearly rate at which `prev` grew to `current` over `seconds`,
+    /// zero when it did not grow
+    pub fn yearly_growth(prev: D128, current: D128, seconds: u64) -> D128 {
+        if seconds == 0 || current <= prev {
+            return Self::zero();
+        }
+
+        let num: u128 = (U256::from(current.num.0 - prev.num.0)
+            * U256::from(DECIMAL * SECONDS_PER_YEAR)
+            / (U256::from(prev.num.0) * U256::from(seconds)))
+        .as_u128();
+
+        Self::new(num)
+    }
 }
 
 impl Add<D128> for D128 {
@@ -0,0 +1,155 @@
+use crate::*;
+
+/// `Config` of the deployed overseer, it served a single market
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OldConfig {
+  pub owner_id: AccountId,
+  pub oracle_contrract: AccountId,
+  pub market_contract: AccountId,
+  pub liquidation_contract: AccountId,
+  pub collector_contract: AccountId,
+  // per block
+  pub target_deposit_rate: D128,
+  pub oracle_payment_token: AccountId,
+  pub requester_contract: AccountId,
+}
+
+/// `State` of the deployed overseer, with a single price for all collateral
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OldState {
+  pub last_price_response: PriceResponse,
+}
+
+/// `WhitelistElem` of the deployed overseer, without a price source
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OldWhitelistElem {
+  pub name: String,
+  pub symbol: String,
+  pub max_ltv: D128,
+  pub custody_contract: AccountId,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OldCollection {
+  pub white_list_elem_map: LookupMap<AccountId, OldWhitelistElem>,
+  pub collateral_map: LookupMap<AccountId, Tokens>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OldContract {
+  pub config: OldConfig,
+  pub state: OldState,
+  pub collection: OldCollection,
+}
+
+#[near_bindgen]
+impl Contract {
+  /// Migrates the deployed overseer. Its whitelist cannot be iterated, so
+  /// `whitelist` lists every collateral token in it with the source of its price;
+  /// `market` describes the market of the old config and `exchange_rate` is its
+  /// aToken exchange rate at the migration. The per block target
+  /// deposit rate is converted with `seconds_per_block`, the average block time
+  /// since the deployment. The collateral map keeps its prefix, so the borrowers
+  /// stay registered, and the price requests restart from the first token.
+  #[private]
+  #[init(ignore_state)]
+  pub fn migrate(
+    market: MarketElem,
+    exchange_rate: D128,
+    whitelist: Vec<(AccountId, PriceSource)>,
+    seconds_per_block: D128,
+  ) -> Self {
+    let mut old: OldContract = env::state_read().expect("Old state doesn't exist");
+    assert!(
+      seconds_per_block > D128::zero(),
+      "seconds_per_block must be positive"
+    );
+
+    let target_deposit_rate: D128 =
+      old.config.target_deposit_rate * D128::new_exp(SECONDS_PER_YEAR, 0) / seconds_per_block;
+    let config = Config {
+      guardian_id: old.config.owner_id.clone(),
+      owner_id: old.config.owner_id,
+      pending_owner_id: None,
+      oracle_contrract: old.config.oracle_contrract,
+      liquidation_contract: old.config.liquidation_contract,
+      collector_contract: old.config.collector_contract,
+      epoch_period: DEFAULT_EPOCH_PERIOD,
+      threshold_deposit_rate: target_deposit_rate,
+      target_deposit_rate,
+      safe_ratio: D128::new_exp(8, -1),
+      liquidation_discount: D128::new_exp(1, -1),
+      buffer_distribution_factor: D128::new_exp(1, -1),
+      price_timeframe: DEFAULT_PRICE_TIMEFRAME,
+      oracle_payment_token: old.config.oracle_payment_token,
+      oracle_payment_amount: DEFAULT_ORACLE_PAYMENT_AMOUNT,
+      requester_contract: old.config.requester_contract,
+    };
+
+    let state = State {
+      price_request_token: None,
+      price_requested_at: 0,
+      paused: PauseFlags::default(),
+    };
+
+    let mut collection = Collection {
+      white_list_elem_map: UnorderedMap::new(StorageKey::WhitelistElem),
+      price_map: LookupMap::new(StorageKey::Price),
+      collateral_map: old.collection.collateral_map,
+      delegate_set: LookupSet::new(StorageKey::Delegate),
+      market_map: UnorderedMap::new(StorageKey::Market),
+      epoch_state_map: LookupMap::new(StorageKey::EpochState),
+      custody_rewards_map: LookupMap::new(StorageKey::CustodyRewards),
+    };
+
+    // the whitelist moves to an iterable map under the same prefix
+    for (collateral_token, price_source) in whitelist.into_iter() {
+      let white_list_elem: OldWhitelistElem =
+        match old.collection.white_list_elem_map.remove(&collateral_token) {
+          Some(value) => value,
+          None => {
+            env::panic(format!("The token {} is not whitelisted", collateral_token).as_bytes())
+          }
+        };
+      collection.white_list_elem_map.insert(
+        &collateral_token,
+        &WhitelistElem {
+          name: white_list_elem.name,
+          symbol: white_list_elem.symbol,
+          max_ltv: white_list_elem.max_ltv,
+          custody_contract: white_list_elem.custody_contract,
+          price_source,
+        },
+      );
+    }
+
+    collection
+      .market_map
+      .insert(&old.config.market_contract, &market);
+    collection.epoch_state_map.insert(
+      &old.config.market_contract,
+      &EpochState {
+        deposit_rate: D128::zero(),
+        prev_exchange_rate: exchange_rate,
+        prev_interest_buffer: 0,
+        last_executed_time: env::block_timestamp() / SECOND_TO_NANO,
+      },
+    );
+
+    let mut instance = Self {
+      config,
+      state,
+      collection,
+      account_storage_usage: 0,
+    };
+    instance.measure_account_storage_usage();
+
+    // the single price of the deployed overseer is dropped, each token gets its own
+    let first_token: Option<AccountId> = instance.collection.white_list_elem_map.keys().next();
+    if let Some(collateral_token) = first_token {
+      instance.internal_create_new_price_request(&collateral_token);
+    }
+
+    instance
+  }
+}
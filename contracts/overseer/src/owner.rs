@@ -6,23 +6,77 @@ impl Contract {
   pub fn update_config(
     &mut self,
     oracle_contrract: Option<AccountId>,
-    market_contract: Option<AccountId>,
     liquidation_contract: Option<AccountId>,
     collector_contract: Option<AccountId>,
+    safe_ratio: Option<D128>,
+    liquidation_discount: Option<D128>,
+    epoch_period: Option<u64>,
+    threshold_deposit_rate: Option<D128>,
+    buffer_distribution_factor: Option<D128>,
+    price_timeframe: Option<u64>,
+    oracle_payment_amount: Option<U128>,
   ) {
     self.assert_owner();
     assert_one_yocto();
     if let Some(oracle_contrract) = oracle_contrract {
       self.config.oracle_contrract = oracle_contrract;
     }
-    if let Some(market_contract) = market_contract {
-      self.config.market_contract = market_contract;
-    }
     if let Some(liquidation_contract) = liquidation_contract {
       self.config.liquidation_contract = liquidation_contract;
     }
     if let Some(collector_contract) = collector_contract {
       self.config.collector_contract = collector_contract;
     }
+    if let Some(safe_ratio) = safe_ratio {
+      if safe_ratio > D128::one() {
+        env::panic(b"safe_ratio must be in [0, 1]");
+      }
+      self.config.safe_ratio = safe_ratio;
+    }
+    if let Some(liquidation_discount) = liquidation_discount {
+      if liquidation_discount >= D128::one() {
+        env::panic(b"liquidation_discount must be in [0, 1)");
+      }
+      self.config.liquidation_discount = liquidation_discount;
+    }
+    if let Some(epoch_period) = epoch_period {
+      self.config.epoch_period = epoch_period;
+    }
+    if let Some(threshold_deposit_rate) = threshold_deposit_rate {
+      self.config.threshold_deposit_rate = threshold_deposit_rate;
+    }
+    if let Some(buffer_distribution_factor) = buffer_distribution_factor {
+      if buffer_distribution_factor > D128::one() {
+        env::panic(b"buffer_distribution_factor must be in [0, 1]");
+      }
+      self.config.buffer_distribution_factor = buffer_distribution_factor;
+    }
+    if let Some(price_timeframe) = price_timeframe {
+      self.config.price_timeframe = price_timeframe;
+    }
+    if let Some(oracle_payment_amount) = oracle_payment_amount {
+      self.config.oracle_payment_amount = oracle_payment_amount.0;
+    }
+
+    Event::UpdateConfig(&self.config).emit();
+  }
+
+  /// Requests the price of `collateral_token`, by default the token of the request
+  /// in flight, when a request never finalizes and the rotation stalls
+  #[payable]
+  pub fn restart_price_requests(&mut self, collateral_token: Option<AccountId>) {
+    assert_one_yocto();
+    self.assert_owner();
+    let collateral_token: AccountId =
+      match collateral_token.or_else(|| self.state.price_request_token.clone()) {
+        Some(collateral_token) => collateral_token,
+        None => env::panic(b"No collateral is whitelisted"),
+      };
+    self.internal_create_new_price_request(&collateral_token);
+
+    Event::RestartPriceRequests {
+      collateral_token: &collateral_token,
+    }
+    .emit();
   }
 }
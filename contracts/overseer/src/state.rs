@@ -4,35 +4,59 @@ use crate::*;
 #[serde(crate = "near_sdk::serde")]
 pub struct Config {
   pub owner_id: AccountId,
+  // proposed owner, set until it accepts the ownership
+  pub pending_owner_id: Option<AccountId>,
+  // can pause operations, only the owner can unpause them
+  pub guardian_id: AccountId,
   pub oracle_contrract: AccountId,
-  pub market_contract: AccountId,
   pub liquidation_contract: AccountId,
   pub collector_contract: AccountId,
-  // pub epoch_period: BlockHeight,
-  // pub threshold_deposit_rate: D128,
+  // seconds between two epoch operations of a market
+  pub epoch_period: u64,
+  // the interest buffer tops up the deposit rate of a market below this rate
+  pub threshold_deposit_rate: D128,
   pub target_deposit_rate: D128,
-  // pub buffer_distribution_factor: D128,
+  // a liquidation brings the loan back to this share of the borrow limit
+  pub safe_ratio: D128,
+  // share of the seized collateral value expected to go to bid premiums and fees
+  pub liquidation_discount: D128,
+  // share of the interest buffer an epoch operation can distribute
+  pub buffer_distribution_factor: D128,
   // pub anc_purchase_factor: D128,
-  // pub price_timeframe: BlockHeight,
+  // seconds an oracle price can be used to compute a borrow limit, it has to cover
+  // a rotation of the price requests over the whitelist
+  pub price_timeframe: u64,
   pub oracle_payment_token: AccountId,
+  // paid for each price request
+  pub oracle_payment_amount: Balance,
   pub requester_contract: AccountId,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub struct State {
-  // pub deposit_rate: D128,
-  // pub prev_stable_coin_total_supply: Balance,
-  // pub prev_exchange_rate: D128,
-  // pub prev_interest_buffer: u128,
-  // pub last_executed_height: BlockHeight,
-  pub last_price_response: PriceResponse,
+  // collateral token priced by the oracle request in flight, one request at a time
+  // as the requester keeps a single request per creator
+  pub price_request_token: Option<AccountId>,
+  // block time in seconds of the request in flight, the oldest its answer can be
+  pub price_requested_at: u64,
+  pub paused: PauseFlags,
 }
 
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct Collection {
-  pub white_list_elem_map: LookupMap<AccountId, WhitelistElem>,
+  pub white_list_elem_map: UnorderedMap<AccountId, WhitelistElem>,
+  // latest oracle price of each collateral token
+  pub price_map: LookupMap<AccountId, PriceResponse>,
   pub collateral_map: LookupMap<AccountId, Tokens>,
+  // (owner, delegate) pairs, a delegate can lock collateral of the owner
+  pub delegate_set: LookupSet<(AccountId, AccountId)>,
+  // stable coin markets, keyed by market contract
+  pub market_map: UnorderedMap<AccountId, MarketElem>,
+  // epoch state of each market, keyed by market contract
+  pub epoch_state_map: LookupMap<AccountId, EpochState>,
+  // collateral rewards received from each custody, keyed by custody contract
+  pub custody_rewards_map: LookupMap<AccountId, CustodyRewards>,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -42,37 +66,69 @@ pub struct WhitelistElem {
   pub symbol: String,
   pub max_ltv: D128,
   pub custody_contract: AccountId,
+  pub price_source: PriceSource,
+}
+
+/// Where the oracle reads the USD price of a collateral token
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PriceSource {
+  pub end_point: String,
+  pub source_path: String,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MarketElem {
+  pub name: String,
+  pub symbol: String,
+  pub stable_coin_contract: AccountId,
+}
+
+/// Rates of a market at its last epoch operation, the market rates are yearly
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EpochState {
+  pub deposit_rate: D128,
+  pub prev_exchange_rate: D128,
+  // overseer balance of the stable coin of the market
+  pub prev_interest_buffer: Balance,
+  pub last_executed_time: u64,
+}
+
+/// Stable coin a custody sent to the interest buffer
+#[derive(
+  BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, Default, PartialEq,
+)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CustodyRewards {
+  pub total_rewards: Balance,
+  pub last_received_at: u64,
 }
 
-#[near_bindgen]
 impl Contract {
-  pub fn add_white_list_elem_map(&mut self, key: &String, value: &WhitelistElem) {
+  pub(crate) fn add_white_list_elem_map(&mut self, key: &String, value: &WhitelistElem) {
     self.collection.white_list_elem_map.insert(&key, value);
   }
 
+  pub(crate) fn add_collateral_map(&mut self, key: &String, value: &Tokens) {
+    self.collection.collateral_map.insert(&key, value);
+  }
+}
+
+#[near_bindgen]
+impl Contract {
   pub fn get_white_list_elem_map(&self, key: &String) -> WhitelistElem {
     match self.collection.white_list_elem_map.get(&key) {
-      Some(value) => {
-        let log_message = format!("Value from LookupMap is {:?}", value.clone());
-        env::log(log_message.as_bytes());
-        value
-      }
-      None => env::panic("".as_bytes()),
+      Some(value) => value,
+      None => env::panic(format!("The token {} is not whitelisted", key).as_bytes()),
     }
   }
 
-  pub fn add_collateral_map(&mut self, key: &String, value: &Tokens) {
-    self.collection.collateral_map.insert(&key, value);
-  }
-
   pub fn get_collateral_map(&self, key: &String) -> Tokens {
     match self.collection.collateral_map.get(&key) {
-      Some(value) => {
-        let log_message = format!("Value from LookupMap is {:?}", value.clone());
-        env::log(log_message.as_bytes());
-        value
-      }
-      None => env::panic("".as_bytes()),
+      Some(value) => value,
+      None => env::panic(format!("The account {} is not registered", key).as_bytes()),
     }
   }
 }
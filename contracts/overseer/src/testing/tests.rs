@@ -1,6 +1,7 @@
 use near_sdk::test_utils::{accounts, VMContextBuilder};
-use near_sdk::{testing_env, MockedBlockchain};
+use near_sdk::{testing_env, MockedBlockchain, RuntimeFeesConfig, VMConfig};
 
+use crate::fungible_token_handler::FungibleTokenReceiver;
 use crate::*;
 
 fn setup_contract() -> (VMContextBuilder, Contract) {
@@ -12,12 +13,730 @@ fn setup_contract() -> (VMContextBuilder, Contract) {
   let contract = Contract::new(
     AccountId::from("owner"),
     AccountId::from("oracle"),
-    AccountId::from("market"),
     AccountId::from("liquidation"),
     AccountId::from("collector"),
-    D128::one(),
+    D128::new_exp(5, -2),
     AccountId::from("oralce_payment_token"),
     AccountId::from("requester"),
   );
   (context, contract)
 }
+
+#[test]
+fn proper_storage_deposit() {
+  let (mut context, mut contract) = setup_contract();
+  let min = contract.storage_balance_bounds().min;
+
+  testing_env!(context
+    .predecessor_account_id(accounts(1))
+    .attached_deposit(min.0)
+    .build());
+  contract.storage_deposit(None, None);
+
+  let tokens: Tokens = vec![];
+  assert_eq!(tokens, contract.get_collateral_map(&accounts(1).to_string()));
+  assert_eq!(min, contract.storage_balance_of(accounts(1)).unwrap().total);
+}
+
+#[test]
+fn proper_approve_delegate() {
+  let (mut context, mut contract) = setup_contract();
+  let min = contract.storage_balance_bounds().min;
+
+  testing_env!(context
+    .predecessor_account_id(accounts(1))
+    .attached_deposit(min.0)
+    .build());
+  contract.storage_deposit(None, None);
+
+  testing_env!(context
+    .predecessor_account_id(accounts(1))
+    .attached_deposit(10u128.pow(22))
+    .build());
+  contract.approve_delegate(accounts(2));
+  assert!(contract.is_delegate(accounts(1).to_string(), accounts(2).to_string()));
+
+  testing_env!(context
+    .predecessor_account_id(accounts(1))
+    .attached_deposit(1)
+    .build());
+  contract.revoke_delegate(accounts(2));
+  assert!(!contract.is_delegate(accounts(1).to_string(), accounts(2).to_string()));
+}
+
+#[test]
+#[should_panic(expected = "charlie is not a delegate of bob")]
+fn lock_collateral_without_approval() {
+  let (mut context, mut contract) = setup_contract();
+
+  testing_env!(context.predecessor_account_id(accounts(2)).build());
+  contract.lock_collateral(vec![], Some(accounts(1).to_string()));
+}
+
+#[test]
+fn proper_register_market() {
+  let (mut context, mut contract) = setup_contract();
+
+  testing_env!(context
+    .predecessor_account_id(ValidAccountId::try_from("owner").unwrap())
+    .attached_deposit(1)
+    .build());
+  contract.register_market(
+    String::from("Tether USD"),
+    String::from("USDT"),
+    AccountId::from("usdt_market"),
+    AccountId::from("usdt"),
+  );
+  contract.register_market(
+    String::from("USD Coin"),
+    String::from("USDC"),
+    AccountId::from("usdc_market"),
+    AccountId::from("usdc"),
+  );
+
+  let markets = contract.get_markets();
+  assert_eq!(2, markets.len());
+  assert_eq!(AccountId::from("usdc_market"), markets[1].0);
+  assert_eq!(AccountId::from("usdc"), markets[1].1.stable_coin_contract);
+}
+
+#[test]
+#[should_panic(expected = "The market usdt_market is already registered")]
+fn register_market_twice() {
+  let (mut context, mut contract) = setup_contract();
+
+  testing_env!(context
+    .predecessor_account_id(ValidAccountId::try_from("owner").unwrap())
+    .attached_deposit(1)
+    .build());
+  for _ in 0..2 {
+    contract.register_market(
+      String::from("Tether USD"),
+      String::from("USDT"),
+      AccountId::from("usdt_market"),
+      AccountId::from("usdt"),
+    );
+  }
+}
+
+#[test]
+fn borrow_limit_net_of_other_markets() {
+  let (mut context, contract) = setup_contract();
+
+  testing_env!(
+    context
+      .predecessor_account_id(accounts(0))
+      .current_account_id(accounts(0))
+      .build(),
+    VMConfig::default(),
+    RuntimeFeesConfig::default(),
+    Default::default(),
+    vec![
+      PromiseResult::Successful(b"\"300\"".to_vec()),
+      PromiseResult::Successful(b"\"200\"".to_vec()),
+    ]
+  );
+
+  let (borrower, borrow_limit) =
+    contract.callback_borrow_limit(accounts(1).to_string(), U128::from(1000u128));
+  assert_eq!(accounts(1).to_string(), borrower);
+  assert_eq!(U128::from(500u128), borrow_limit);
+
+  // loans over the limit leave nothing to borrow
+  let (_, borrow_limit) =
+    contract.callback_borrow_limit(accounts(1).to_string(), U128::from(400u128));
+  assert_eq!(U128::from(0u128), borrow_limit);
+}
+
+fn register_collateral(
+  context: &mut VMContextBuilder,
+  contract: &mut Contract,
+  collateral_token: &str,
+  max_ltv: D128,
+) {
+  testing_env!(context
+    .predecessor_account_id(ValidAccountId::try_from("owner").unwrap())
+    .attached_deposit(1)
+    .build());
+  contract.register_whitelist(
+    collateral_token.to_uppercase(),
+    collateral_token.to_uppercase(),
+    AccountId::from(collateral_token),
+    format!("custody_{}", collateral_token),
+    max_ltv,
+    PriceSource {
+      end_point: String::from("https://api.coingecko.com/api/v3/simple/price"),
+      source_path: format!("{}.usd", collateral_token),
+    },
+  );
+}
+
+#[test]
+fn borrow_limit_with_collateral_prices() {
+  let (mut context, mut contract) = setup_contract();
+  register_collateral(&mut context, &mut contract, "bnear", D128::new_exp(5, -1));
+  register_collateral(&mut context, &mut contract, "beth", D128::new_exp(6, -1));
+
+  for (collateral_token, price) in vec![("bnear", 10u128), ("beth", 2000u128)] {
+    contract.collection.price_map.insert(
+      &AccountId::from(collateral_token),
+      &PriceResponse {
+        price: D128::new_exp(price, 0),
+        last_updated_at: 0,
+      },
+    );
+  }
+
+  let collaterals: Tokens = vec![
+    (AccountId::from("bnear"), 100u128),
+    (AccountId::from("beth"), 1u128),
+  ];
+  let (borrow_limit, collateral_prices) = contract.compute_borrow_limit(&collaterals, None);
+  // 100 * 10 * 0.5 + 1 * 2000 * 0.6
+  assert_eq!(1700u128, borrow_limit);
+  assert_eq!(
+    (AccountId::from("beth"), D128::new_exp(2000, 0)),
+    collateral_prices[1]
+  );
+}
+
+#[test]
+#[should_panic(expected = "No price for the token beth")]
+fn borrow_limit_without_price() {
+  let (mut context, mut contract) = setup_contract();
+  register_collateral(&mut context, &mut contract, "beth", D128::new_exp(6, -1));
+
+  contract.compute_borrow_limit(&vec![(AccountId::from("beth"), 1u128)], None);
+}
+
+#[test]
+fn borrow_limit_with_fresh_price() {
+  let (mut context, mut contract) = setup_contract();
+  register_collateral(&mut context, &mut contract, "bnear", D128::new_exp(5, -1));
+  contract.collection.price_map.insert(
+    &AccountId::from("bnear"),
+    &PriceResponse {
+      price: D128::new_exp(10, 0),
+      last_updated_at: 100,
+    },
+  );
+
+  // the default price timeframe is 900 seconds
+  let (borrow_limit, _) = contract.compute_borrow_limit(
+    &vec![(AccountId::from("bnear"), 100u128)],
+    Some(1_000),
+  );
+  assert_eq!(500u128, borrow_limit);
+}
+
+#[test]
+#[should_panic(
+  expected = "The price of bnear is too old: updated 1000 seconds ago, the price timeframe is 900 seconds"
+)]
+fn borrow_limit_with_old_price() {
+  let (mut context, mut contract) = setup_contract();
+  register_collateral(&mut context, &mut contract, "bnear", D128::new_exp(5, -1));
+  contract.collection.price_map.insert(
+    &AccountId::from("bnear"),
+    &PriceResponse {
+      price: D128::new_exp(10, 0),
+      last_updated_at: 100,
+    },
+  );
+
+  contract.compute_borrow_limit(
+    &vec![(AccountId::from("bnear"), 100u128)],
+    Some(1_100),
+  );
+}
+
+#[test]
+fn borrow_limit_with_price_timeframe_over_rotation() {
+  let (mut context, mut contract) = setup_contract();
+  register_collateral(&mut context, &mut contract, "bnear", D128::new_exp(5, -1));
+  register_collateral(&mut context, &mut contract, "beth", D128::new_exp(6, -1));
+  contract.collection.price_map.insert(
+    &AccountId::from("bnear"),
+    &PriceResponse {
+      price: D128::new_exp(10, 0),
+      last_updated_at: 100,
+    },
+  );
+
+  // two whitelisted tokens, the owner covers a rotation of 1800 seconds
+  contract.config.price_timeframe = 1_800;
+  let (borrow_limit, _) = contract.compute_borrow_limit(
+    &vec![(AccountId::from("bnear"), 100u128)],
+    Some(1_100),
+  );
+  assert_eq!(500u128, borrow_limit);
+}
+
+#[test]
+fn price_requests_rotate_over_whitelist() {
+  let (mut context, mut contract) = setup_contract();
+  register_collateral(&mut context, &mut contract, "bnear", D128::new_exp(5, -1));
+  register_collateral(&mut context, &mut contract, "beth", D128::new_exp(6, -1));
+
+  // the first whitelisted token is requested first
+  assert_eq!(
+    Some(AccountId::from("bnear")),
+    contract.state.price_request_token
+  );
+  assert_eq!(
+    AccountId::from("beth"),
+    contract.internal_next_price_token(&AccountId::from("bnear"))
+  );
+  assert_eq!(
+    AccountId::from("bnear"),
+    contract.internal_next_price_token(&AccountId::from("beth"))
+  );
+}
+
+#[test]
+fn restart_stalled_price_requests() {
+  let (mut context, mut contract) = setup_contract();
+  register_collateral(&mut context, &mut contract, "bnear", D128::new_exp(5, -1));
+  register_collateral(&mut context, &mut contract, "beth", D128::new_exp(6, -1));
+
+  // the request in flight is requested again
+  testing_env!(context
+    .predecessor_account_id(ValidAccountId::try_from("owner").unwrap())
+    .attached_deposit(1)
+    .block_timestamp(3_600 * SECOND_TO_NANO)
+    .build());
+  contract.restart_price_requests(None);
+  assert_eq!(
+    Some(AccountId::from("bnear")),
+    contract.state.price_request_token
+  );
+  assert_eq!(3_600, contract.state.price_requested_at);
+
+  // or the rotation moves on to another token
+  testing_env!(context.build());
+  contract.restart_price_requests(Some(AccountId::from("beth")));
+  assert_eq!(
+    Some(AccountId::from("beth")),
+    contract.state.price_request_token
+  );
+}
+
+#[test]
+#[should_panic(expected = "Can only be called by the owner")]
+fn restart_price_requests_by_non_owner() {
+  let (mut context, mut contract) = setup_contract();
+  register_collateral(&mut context, &mut contract, "bnear", D128::new_exp(5, -1));
+
+  testing_env!(context
+    .predecessor_account_id(accounts(1))
+    .attached_deposit(1)
+    .build());
+  contract.restart_price_requests(None);
+}
+
+#[test]
+#[should_panic(expected = "Can only be called by the owner")]
+fn register_whitelist_by_non_owner() {
+  let (mut context, mut contract) = setup_contract();
+
+  testing_env!(context
+    .predecessor_account_id(accounts(1))
+    .attached_deposit(1)
+    .build());
+  contract.register_whitelist(
+    String::from("BNEAR"),
+    String::from("BNEAR"),
+    AccountId::from("bnear"),
+    AccountId::from("custody_bnear"),
+    D128::new_exp(5, -1),
+    PriceSource {
+      end_point: String::from("https://api.coingecko.com/api/v3/simple/price"),
+      source_path: String::from("bnear.usd"),
+    },
+  );
+}
+
+#[test]
+fn proper_liquidation_amount() {
+  let (_, contract) = setup_contract();
+  let collaterals: Tokens = vec![
+    (AccountId::from("beth"), 10u128),
+    (AccountId::from("bnear"), 100u128),
+  ];
+  let collateral_prices: Vec<(AccountId, D128)> = vec![
+    (AccountId::from("beth"), D128::new_exp(100, 0)),
+    (AccountId::from("bnear"), D128::new_exp(10, 0)),
+  ];
+
+  // safe_borrow = 1000 * 0.8, expected_repay = 2000 * 0.9
+  // ratio = (1100 - 800) / (1800 - 800) = 0.3
+  assert_eq!(
+    vec![
+      (AccountId::from("beth"), 3u128),
+      (AccountId::from("bnear"), 30u128)
+    ],
+    contract.compute_liquidation_amount(1100, 1100, 1000, &collaterals, &collateral_prices)
+  );
+
+  // the collateral can't repay the loan even after all of it is sold
+  assert_eq!(
+    collaterals,
+    contract.compute_liquidation_amount(1900, 1900, 1000, &collaterals, &collateral_prices)
+  );
+
+  // only 180 of the loan is in the repaid market, ratio = 180 / 1800 = 0.1
+  assert_eq!(
+    vec![
+      (AccountId::from("beth"), 1u128),
+      (AccountId::from("bnear"), 10u128)
+    ],
+    contract.compute_liquidation_amount(1100, 180, 1000, &collaterals, &collateral_prices)
+  );
+}
+
+#[test]
+#[should_panic(expected = "No loan to repay in the market usdc_market")]
+fn liquidate_collateral_without_loan_in_market() {
+  let (mut context, mut contract) = setup_contract();
+  register_usdt_market(&mut context, &mut contract);
+  contract.register_market(
+    String::from("USD Coin"),
+    String::from("USDC"),
+    AccountId::from("usdc_market"),
+    AccountId::from("usdc"),
+  );
+
+  testing_env!(
+    context
+      .predecessor_account_id(accounts(0))
+      .current_account_id(accounts(0))
+      .build(),
+    VMConfig::default(),
+    RuntimeFeesConfig::default(),
+    Default::default(),
+    vec![
+      PromiseResult::Successful(b"\"1000\"".to_vec()),
+      PromiseResult::Successful(b"\"0\"".to_vec()),
+    ]
+  );
+  contract.callback_liquidate_collateral(
+    accounts(1).to_string(),
+    accounts(2).to_string(),
+    AccountId::from("usdc_market"),
+    vec![(AccountId::from("bnear"), 100u128)],
+    500,
+    vec![(AccountId::from("bnear"), D128::new_exp(10, 0))],
+  );
+}
+
+#[test]
+fn liquidation_result_keeps_failed_collateral() {
+  let (mut context, mut contract) = setup_contract();
+  let borrower: AccountId = accounts(1).to_string();
+  contract.add_collateral_map(
+    &borrower,
+    &vec![
+      (AccountId::from("beth"), 10u128),
+      (AccountId::from("bnear"), 100u128),
+    ],
+  );
+
+  testing_env!(
+    context
+      .predecessor_account_id(accounts(0))
+      .current_account_id(accounts(0))
+      .build(),
+    VMConfig::default(),
+    RuntimeFeesConfig::default(),
+    Default::default(),
+    vec![
+      PromiseResult::Failed,
+      PromiseResult::Successful(b"\"30\"".to_vec()),
+    ]
+  );
+
+  contract.callback_liquidation_result(
+    borrower.clone(),
+    vec![
+      (AccountId::from("beth"), 3u128),
+      (AccountId::from("bnear"), 30u128),
+    ],
+  );
+  assert_eq!(
+    vec![
+      (AccountId::from("beth"), 10u128),
+      (AccountId::from("bnear"), 70u128)
+    ],
+    contract.get_collateral_map(&borrower)
+  );
+}
+
+#[test]
+#[should_panic(expected = "No collateral was liquidated")]
+fn liquidation_result_without_liquidation() {
+  let (mut context, mut contract) = setup_contract();
+
+  testing_env!(
+    context
+      .predecessor_account_id(accounts(0))
+      .current_account_id(accounts(0))
+      .build(),
+    VMConfig::default(),
+    RuntimeFeesConfig::default(),
+    Default::default(),
+    vec![PromiseResult::Failed]
+  );
+
+  contract.callback_liquidation_result(
+    accounts(1).to_string(),
+    vec![(AccountId::from("bnear"), 30u128)],
+  );
+}
+
+fn register_usdt_market(context: &mut VMContextBuilder, contract: &mut Contract) {
+  testing_env!(context
+    .predecessor_account_id(ValidAccountId::try_from("owner").unwrap())
+    .attached_deposit(1)
+    .block_timestamp(0)
+    .build());
+  contract.register_market(
+    String::from("Tether USD"),
+    String::from("USDT"),
+    AccountId::from("usdt_market"),
+    AccountId::from("usdt"),
+  );
+}
+
+#[test]
+fn proper_epoch_operations() {
+  let (mut context, mut contract) = setup_contract();
+  register_usdt_market(&mut context, &mut contract);
+
+  let market_epoch_state = EpochStateResponse {
+    exchange_rate: D128::one(),
+    atoken_supply: U128::from(1_000_000_000_000u128),
+    deposit_rate: D128::zero(),
+    total_liabilities: D128::zero(),
+    total_reserves: D128::zero(),
+  };
+  testing_env!(
+    context
+      .predecessor_account_id(accounts(0))
+      .current_account_id(accounts(0))
+      .block_timestamp(3_600 * SECOND_TO_NANO)
+      .build(),
+    VMConfig::default(),
+    RuntimeFeesConfig::default(),
+    Default::default(),
+    vec![
+      PromiseResult::Successful(b"\"1000000000000\"".to_vec()),
+      PromiseResult::Successful(near_sdk::serde_json::to_vec(&market_epoch_state).unwrap()),
+    ]
+  );
+
+  // no interest accrued, an hour at the 5% threshold is
+  // 1_000_000_000_000 * 0.05 * 3_600 / 31_536_000
+  assert_eq!(
+    5_707_762u128,
+    contract.compute_distributed_interest(
+      D128::zero(),
+      1_000_000_000_000,
+      3_600,
+      1_000_000_000_000
+    )
+  );
+  // the buffer distributes at most 1_000_000 * 0.1
+  assert_eq!(
+    100_000u128,
+    contract.compute_distributed_interest(D128::zero(), 1_000_000_000_000, 3_600, 1_000_000)
+  );
+  contract.callback_execute_epoch_operations(AccountId::from("usdt_market"), 3_600);
+  assert_eq!(
+    3_600,
+    contract
+      .get_epoch_state(AccountId::from("usdt_market"))
+      .last_executed_time
+  );
+
+  testing_env!(
+    context.build(),
+    VMConfig::default(),
+    RuntimeFeesConfig::default(),
+    Default::default(),
+    vec![PromiseResult::Successful(b"\"5707762\"".to_vec())]
+  );
+
+  contract.callback_distribute_interest(
+    AccountId::from("usdt_market"),
+    3_600,
+    D128::one(),
+    U128::from(1_000_000_000_000u128),
+    U128::from(1_000_000_000_000u128),
+  );
+  let epoch_state = contract.get_epoch_state(AccountId::from("usdt_market"));
+  // exchange_rate = 1 + 5_707_762 / 1_000_000_000_000
+  assert_eq!(D128::new(1_00000570), epoch_state.prev_exchange_rate);
+  // 0.0000057 an hour is 4.99% a year
+  assert_eq!(D128::new(4_993_200), epoch_state.deposit_rate);
+  assert_eq!(999_994_292_238, epoch_state.prev_interest_buffer);
+}
+
+#[test]
+#[should_panic(expected = "The epoch period has not passed")]
+fn epoch_operations_before_epoch_period() {
+  let (mut context, mut contract) = setup_contract();
+  register_usdt_market(&mut context, &mut contract);
+
+  testing_env!(context.block_timestamp(1_800 * SECOND_TO_NANO).build());
+  contract.execute_epoch_operations(AccountId::from("usdt_market"));
+}
+
+#[test]
+fn epoch_operations_with_gas_for_one_custody() {
+  let (mut context, mut contract) = setup_contract();
+  register_usdt_market(&mut context, &mut contract);
+  register_collateral(&mut context, &mut contract, "bnear", D128::new_exp(5, -1));
+  register_collateral(&mut context, &mut contract, "beth", D128::new_exp(6, -1));
+
+  // 300 - 2 * 10 - 140 - 40 leaves 100 TGas, enough for a single custody
+  testing_env!(context
+    .predecessor_account_id(accounts(1))
+    .block_timestamp(3_600 * SECOND_TO_NANO)
+    .prepaid_gas(300_000_000_000_000)
+    .build());
+  contract.execute_epoch_operations(AccountId::from("usdt_market"));
+  assert_eq!(
+    near_sdk::test_utils::get_logs(),
+    vec!["Not enough gas to distribute the rewards of custody_beth"]
+  );
+}
+
+#[test]
+fn proper_collateral_rewards() {
+  let (mut context, mut contract) = setup_contract();
+  register_usdt_market(&mut context, &mut contract);
+  register_collateral(&mut context, &mut contract, "bnear", D128::new_exp(5, -1));
+
+  testing_env!(context
+    .predecessor_account_id(ValidAccountId::try_from("usdt").unwrap())
+    .build());
+
+  // only a custody can fund the interest buffer
+  match contract.ft_on_transfer(
+    AccountId::from("depositor"),
+    U128::from(100u128),
+    String::from("\"collateral_rewards\""),
+  ) {
+    PromiseOrValue::Value(refund_amount) => assert_eq!(U128::from(100u128), refund_amount),
+    PromiseOrValue::Promise(_) => panic!("unexpected promise"),
+  }
+
+  for _ in 0..2 {
+    match contract.ft_on_transfer(
+      AccountId::from("custody_bnear"),
+      U128::from(100u128),
+      String::from("\"collateral_rewards\""),
+    ) {
+      PromiseOrValue::Value(refund_amount) => assert_eq!(U128::from(0u128), refund_amount),
+      PromiseOrValue::Promise(_) => panic!("unexpected promise"),
+    }
+  }
+  assert_eq!(
+    200,
+    contract
+      .get_custody_rewards(AccountId::from("custody_bnear"))
+      .total_rewards
+  );
+  assert_eq!(
+    CustodyRewards::default(),
+    contract.get_custody_rewards(AccountId::from("depositor"))
+  );
+}
+
+#[test]
+fn migrate_from_deployed_overseer() {
+  let mut context = VMContextBuilder::new();
+  testing_env!(context
+    .current_account_id(accounts(0))
+    .block_timestamp(10_000 * SECOND_TO_NANO)
+    .build());
+
+  let mut white_list_elem_map = LookupMap::new(StorageKey::WhitelistElem);
+  white_list_elem_map.insert(
+    &AccountId::from("bnear"),
+    &migration::OldWhitelistElem {
+      name: String::from("bNEAR"),
+      symbol: String::from("bNEAR"),
+      max_ltv: D128::new_exp(5, -1),
+      custody_contract: AccountId::from("custody_bnear"),
+    },
+  );
+  let mut collateral_map = LookupMap::new(StorageKey::Collateral);
+  collateral_map.insert(
+    &accounts(1).to_string(),
+    &vec![(AccountId::from("bnear"), 100u128)],
+  );
+  env::state_write(&migration::OldContract {
+    config: migration::OldConfig {
+      owner_id: AccountId::from("owner"),
+      oracle_contrract: AccountId::from("oracle"),
+      market_contract: AccountId::from("usdt_market"),
+      liquidation_contract: AccountId::from("liquidation"),
+      collector_contract: AccountId::from("collector"),
+      target_deposit_rate: D128::new_exp(1, -8),
+      oracle_payment_token: AccountId::from("oralce_payment_token"),
+      requester_contract: AccountId::from("requester"),
+    },
+    state: migration::OldState {
+      last_price_response: PriceResponse {
+        price: D128::one(),
+        last_updated_at: 0,
+      },
+    },
+    collection: migration::OldCollection {
+      white_list_elem_map,
+      collateral_map,
+    },
+  });
+
+  let price_source = PriceSource {
+    end_point: String::from("https://api.example.com/near"),
+    source_path: String::from("price"),
+  };
+  let contract = Contract::migrate(
+    MarketElem {
+      name: String::from("Tether USD"),
+      symbol: String::from("USDT"),
+      stable_coin_contract: AccountId::from("usdt"),
+    },
+    D128::new_exp(11, -1),
+    vec![(AccountId::from("bnear"), price_source.clone())],
+    D128::new_exp(2, 0),
+  );
+
+  // 0.00000001 a block of 2 seconds is 0.15768 a year
+  let config = contract.get_config();
+  assert_eq!(D128::new_exp(15768, -5), config.target_deposit_rate);
+  assert_eq!(config.target_deposit_rate, config.threshold_deposit_rate);
+  assert!(config.pending_owner_id.is_none());
+
+  let white_list_elem = contract.get_white_list_elem_map(&AccountId::from("bnear"));
+  assert_eq!(D128::new_exp(5, -1), white_list_elem.max_ltv);
+  assert_eq!(price_source, white_list_elem.price_source);
+
+  // the borrowers keep their collateral and registration
+  assert_eq!(
+    vec![(AccountId::from("bnear"), 100u128)],
+    contract.get_collateral_map(&accounts(1).to_string())
+  );
+  assert!(contract.storage_balance_of(accounts(1)).is_some());
+
+  let epoch_state = contract.get_epoch_state(AccountId::from("usdt_market"));
+  assert_eq!(D128::new_exp(11, -1), epoch_state.prev_exchange_rate);
+  assert_eq!(10_000, epoch_state.last_executed_time);
+  assert_eq!(
+    Some(AccountId::from("bnear")),
+    contract.get_state().price_request_token
+  );
+  assert_eq!(PauseFlags::default(), contract.get_state().paused);
+}
@@ -1,3 +1,4 @@
+use crate::internal::compute_deposit_rate;
 use crate::*;
 
 use flux_sdk::consts::{DR_NEW_GAS, GAS_BASE_TRANSFER};
@@ -22,11 +23,26 @@ pub trait FungibleToken {
 
 #[ext_contract(ext_market)]
 pub trait MarketContract {
-  fn get_borrower_info(
+  fn get_loan_amount(&self, borrower: AccountId, block_time: Option<u64>) -> U128;
+
+  fn get_epoch_state(
+    &self,
+    block_time: Option<u64>,
+    distributed_interest: Option<U128>,
+  ) -> EpochStateResponse;
+
+  fn execute_epoch_operations(
     &mut self,
-    borrower: AccountId,
-    block_height: Option<BlockHeight>,
-  ) -> BorrowerInfo;
+    deposit_rate: D128,
+    target_deposit_rate: D128,
+    threshold_deposit_rate: D128,
+    distributed_intereset: U128,
+  );
+}
+
+#[ext_contract(ext_requester)]
+pub trait RequesterContract {
+  fn get_data_request(&self, request_id: AccountId) -> Option<DataRequestDetails>;
 }
 
 #[ext_contract(ext_custody_bnear)]
@@ -34,34 +50,56 @@ pub trait CustodyBnearContract {
   fn lock_collateral(&mut self, borrower: AccountId, amount: Balance) -> Promise;
 
   fn unlock_collateral(&mut self, borrower: AccountId, amount: Balance) -> Promise;
+
+  fn liquidate_collateral(
+    &mut self,
+    liquidator: AccountId,
+    borrower: AccountId,
+    amount: Balance,
+    repay_address: AccountId,
+    fee_address: AccountId,
+  ) -> U128;
+
+  fn distribute_rewards(&self) -> Promise;
 }
 
 #[ext_contract(ext_self)]
 pub trait Contract {
+  fn callback_get_price_response(&mut self, collateral_token: AccountId);
+
   fn callback_unlock_collateral(
     &self,
     borrower: AccountId,
+    collaterals: Tokens,
     cur_collaterals: Tokens,
     borrow_limit: u128,
-    block_height: BlockHeight,
+    block_time: u64,
   );
 
+  fn callback_borrow_limit(&self, borrower: AccountId, borrow_limit: U128) -> (AccountId, U128);
+
   fn callback_liquidate_collateral(
     &self,
     borrower: AccountId,
+    liquidator: AccountId,
+    market_contract: AccountId,
     cur_collaterals: Tokens,
     borrow_limit: u128,
-    block_height: BlockHeight,
+    collateral_prices: Vec<(AccountId, D128)>,
   );
-}
 
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
-#[serde(crate = "near_sdk::serde")]
-pub struct BorrowerInfo {
-  pub interest_index: D128,
-  pub reward_index: D128,
-  pub loan_amount: Balance,
-  pub pending_rewards: D128,
+  fn callback_liquidation_result(&mut self, borrower: AccountId, liquidation_amount: Tokens);
+
+  fn callback_execute_epoch_operations(&mut self, market_contract: AccountId, block_time: u64);
+
+  fn callback_distribute_interest(
+    &mut self,
+    market_contract: AccountId,
+    passed_time: u64,
+    exchange_rate: D128,
+    atoken_supply: U128,
+    interest_buffer: U128,
+  );
 }
 
 pub fn fungible_token_transfer_call(
@@ -84,100 +122,302 @@ pub fn fungible_token_transfer_call(
 
 #[near_bindgen]
 impl Contract {
+  /// Stores the price of `collateral_token` once its request is finalized,
+  /// then requests the price of the next whitelisted token
   #[private]
-  pub fn callback_get_price_response(&mut self, #[callback] result: Option<DataRequestDetails>) {
+  pub fn callback_get_price_response(
+    &mut self,
+    collateral_token: AccountId,
+    #[callback] result: Option<DataRequestDetails>,
+  ) {
     let result: DataRequestDetails = result.expect("ERR: There is no response.");
 
+    // a callback of an older fetch, the request has moved on
+    if self.state.price_request_token.as_ref() != Some(&collateral_token)
+      || !result.tags.contains(&collateral_token)
+    {
+      return;
+    }
+
     let status: RequestStatus = result.status;
 
     if let RequestStatus::Finalized(outcome) = status {
       if let Outcome::Answer(answer_type) = outcome {
         if let AnswerType::Number(number) = answer_type {
-          // store latest price response
-          self.state.last_price_response = PriceResponse {
-            price: D128::new(number.value.0),
-            last_updated_at: env::block_timestamp(),
-          };
-          // create new price request
-          self.internal_create_new_price_request();
+          // store latest price response, the request details carry no resolution
+          // time, so the price is dated by its request as the answer can't be older
+          self.collection.price_map.insert(
+            &collateral_token,
+            &PriceResponse {
+              price: D128::new(number.value.0),
+              last_updated_at: self.state.price_requested_at,
+            },
+          );
         }
       }
+      // create new price request, a bad answer is retried in the next rotation
+      let next_token: AccountId = self.internal_next_price_token(&collateral_token);
+      self.internal_create_new_price_request(&next_token);
     }
   }
 
   #[private]
-  fn callback_unlock_collateral(
+  pub fn callback_unlock_collateral(
     &mut self,
     borrower: AccountId,
+    collaterals: Tokens,
     cur_collaterals: Tokens,
     borrow_limit: u128,
-    block_height: BlockHeight,
+    block_time: u64,
   ) {
-    assert_eq!(env::promise_results_count(), 1, "This is a callback method");
+    let loan_amount: Balance = self.internal_total_loan_amount();
+    if borrow_limit < loan_amount {
+      env::panic("UnlockTooLarge".as_bytes());
+    }
 
-    match env::promise_result(0) {
-      PromiseResult::NotReady => unreachable!(),
-      PromiseResult::Failed => {
-        env::panic("fail".as_bytes());
-      }
-      PromiseResult::Successful(result) => {
-        let borrowerInfo: BorrowerInfo =
-          near_sdk::serde_json::from_slice::<BorrowerInfo>(&result).unwrap();
-        if borrow_limit < borrowerInfo.loan_amount {
-          env::panic("UnlockTooLarge".as_bytes());
-        }
+    self.add_collateral_map(&borrower, &cur_collaterals);
 
-        self.add_collateral_map(&borrower, &cur_collaterals);
-
-        for collateral in cur_collaterals.clone() {
-          let white_list_elem: WhitelistElem = self.get_white_list_elem_map(&collateral.0);
-          // TODO handle result with {borrwer, amount} from custody
-          ext_custody_bnear::unlock_collateral(
-            borrower.clone(),
-            collateral.1,
-            &white_list_elem.custody_contract,
-            NO_DEPOSIT,
-            SINGLE_CALL_GAS,
-          );
-        }
-      }
+    Event::UnlockCollateral {
+      borrower: &borrower,
+      collaterals: &collaterals,
+    }
+    .emit();
+
+    for collateral in cur_collaterals.clone() {
+      let white_list_elem: WhitelistElem = self.get_white_list_elem_map(&collateral.0);
+      // TODO handle result with {borrwer, amount} from custody
+      ext_custody_bnear::unlock_collateral(
+        borrower.clone(),
+        collateral.1,
+        &white_list_elem.custody_contract,
+        NO_DEPOSIT,
+        SINGLE_CALL_GAS,
+      );
     }
   }
 
+  /// Borrow limit left after the loans in the queried markets
   #[private]
-  fn callback_liquidate_collateral(
+  pub fn callback_borrow_limit(
+    &self,
+    borrower: AccountId,
+    borrow_limit: U128,
+  ) -> (AccountId, U128) {
+    let loan_amount: Balance = self.internal_total_loan_amount();
+
+    (
+      borrower,
+      U128::from(borrow_limit.0.saturating_sub(loan_amount)),
+    )
+  }
+
+  #[private]
+  pub fn callback_liquidate_collateral(
     &mut self,
     borrower: AccountId,
+    liquidator: AccountId,
+    market_contract: AccountId,
     cur_collaterals: Tokens,
     borrow_limit: u128,
-    block_height: BlockHeight,
+    collateral_prices: Vec<(AccountId, D128)>,
   ) {
-    assert_eq!(env::promise_results_count(), 1, "This is a callback method");
+    let borrow_amount: Balance = self.internal_total_loan_amount();
+    if borrow_limit >= borrow_amount {
+      env::panic("CannotLiquidationSafeLoan".as_bytes());
+    }
+    let market_loan_amount: Balance = self.internal_market_loan_amount(&market_contract);
+    if market_loan_amount == 0 {
+      env::panic(format!("No loan to repay in the market {}", market_contract).as_bytes());
+    }
 
-    match env::promise_result(0) {
-      PromiseResult::NotReady => unreachable!(),
-      PromiseResult::Failed => {
-        env::panic("fail".as_bytes());
-      }
-      PromiseResult::Successful(result) => {
-        let borrowerInfo: BorrowerInfo =
-          near_sdk::serde_json::from_slice::<BorrowerInfo>(&result).unwrap();
-        let borrow_amount = borrowerInfo.loan_amount;
-        if borrow_limit >= borrow_amount {
-          env::panic("CannotLiquidationSafeLoan".as_bytes());
-        }
+    let liquidation_amount: Tokens = self.compute_liquidation_amount(
+      borrow_amount,
+      market_loan_amount,
+      borrow_limit,
+      &cur_collaterals,
+      &collateral_prices,
+    );
+    if liquidation_amount.is_empty() {
+      env::panic("No collateral to liquidate".as_bytes());
+    }
+
+    let custody_gas: Gas =
+      (env::prepaid_gas() - env::used_gas() - LIQUIDATION_RESULT_GAS - GAS_RESERVE)
+        / liquidation_amount.len() as u64;
+
+    // custody sends the collateral to the liquidation queue, which repays the loan
+    // in the market before the collateral transfer resolves
+    liquidation_amount
+      .iter()
+      .map(|collateral| {
+        let white_list_elem: WhitelistElem = self.get_white_list_elem_map(&collateral.0);
+        ext_custody_bnear::liquidate_collateral(
+          liquidator.clone(),
+          borrower.clone(),
+          collateral.1,
+          market_contract.clone(),
+          self.config.collector_contract.clone(),
+          &white_list_elem.custody_contract,
+          NO_DEPOSIT,
+          custody_gas,
+        )
+      })
+      .reduce(|promise, next| promise.and(next))
+      .unwrap()
+      .then(ext_self::callback_liquidation_result(
+        borrower,
+        liquidation_amount,
+        &env::current_account_id(),
+        NO_DEPOSIT,
+        LIQUIDATION_RESULT_GAS,
+      ));
+  }
+
+  /// Removes the collateral the liquidation queue took from the borrower,
+  /// the loan is already repaid with the proceeds
+  #[private]
+  pub fn callback_liquidation_result(&mut self, borrower: AccountId, liquidation_amount: Tokens) {
+    // each custody returns the amount it liquidated, a failed one liquidated nothing
+    let liquidated: Tokens = liquidation_amount
+      .into_iter()
+      .enumerate()
+      .map(
+        |(index, collateral)| match env::promise_result(index as u64) {
+          PromiseResult::Successful(result) => (
+            collateral.0,
+            near_sdk::serde_json::from_slice::<U128>(&result).unwrap().0,
+          ),
+          _ => (collateral.0, 0),
+        },
+      )
+      .filter(|collateral| collateral.1 > 0)
+      .collect();
 
-        let liquidation_amount: Tokens = vec![(String::from(""), 0)]; // TODO: need to cross-contract call to liquidation contract
+    if liquidated.is_empty() {
+      env::panic("No collateral was liquidated".as_bytes());
+    }
 
-        let mut latest_collarterals = cur_collaterals.clone();
+    let mut cur_collaterals: Tokens = self.get_collateral_map(&borrower);
+    cur_collaterals.sub(liquidated.clone());
+    self.add_collateral_map(&borrower, &cur_collaterals);
 
-        latest_collarterals.sub(liquidation_amount.clone());
-        self.add_collateral_map(&borrower, &latest_collarterals);
+    Event::LiquidateCollateral {
+      borrower: &borrower,
+      collaterals: &liquidated,
+    }
+    .emit();
+  }
 
-        let prev_balance = 0; // TODO: need to cross-contract call to market contract
+  /// Distributes the interest buffer to the market when its deposit rate is below
+  /// `threshold_deposit_rate`, then lets the market run its epoch operations
+  #[private]
+  pub fn callback_execute_epoch_operations(
+    &mut self,
+    market_contract: AccountId,
+    block_time: u64,
+  ) -> Promise {
+    // another call may have run the epoch operations in the meantime
+    self.assert_epoch_passed(&market_contract, block_time);
 
-        // TODO: handle with Custody Contract
+    let interest_buffer: Balance = match env::promise_result(0) {
+      PromiseResult::Successful(result) => {
+        near_sdk::serde_json::from_slice::<U128>(&result).unwrap().0
       }
+      _ => env::panic(b"Failed to fetch the interest buffer"),
+    };
+    let market_epoch_state: EpochStateResponse = match env::promise_result(1) {
+      PromiseResult::Successful(result) => near_sdk::serde_json::from_slice(&result).unwrap(),
+      _ => env::panic(b"Failed to fetch the epoch state"),
+    };
+
+    let mut epoch_state: EpochState = self
+      .collection
+      .epoch_state_map
+      .get(&market_contract)
+      .unwrap();
+    let passed_time: u64 = block_time - epoch_state.last_executed_time;
+    epoch_state.last_executed_time = block_time;
+    self
+      .collection
+      .epoch_state_map
+      .insert(&market_contract, &epoch_state);
+
+    let deposit_rate: D128 = compute_deposit_rate(
+      epoch_state.prev_exchange_rate,
+      market_epoch_state.exchange_rate,
+      passed_time,
+    );
+    let prev_deposits: Balance = epoch_state
+      .prev_exchange_rate
+      .mul_int(market_epoch_state.atoken_supply.0);
+    let distributed_interest: Balance =
+      self.compute_distributed_interest(deposit_rate, prev_deposits, passed_time, interest_buffer);
+
+    if distributed_interest == 0 {
+      return self.internal_update_epoch_state(
+        market_contract,
+        passed_time,
+        market_epoch_state.exchange_rate,
+        market_epoch_state.atoken_supply.0,
+        interest_buffer,
+        0,
+      );
     }
+
+    let stable_coin_contract: AccountId = self
+      .collection
+      .market_map
+      .get(&market_contract)
+      .unwrap()
+      .stable_coin_contract;
+
+    // the market adds the interest to its balance without minting aToken
+    fungible_token::ft_transfer_call(
+      market_contract.clone(),
+      U128::from(distributed_interest),
+      None,
+      String::from("\"distribute_interest\""),
+      &stable_coin_contract,
+      1,
+      DISTRIBUTE_INTEREST_GAS,
+    )
+    .then(ext_self::callback_distribute_interest(
+      market_contract,
+      passed_time,
+      market_epoch_state.exchange_rate,
+      market_epoch_state.atoken_supply,
+      U128::from(interest_buffer),
+      &env::current_account_id(),
+      NO_DEPOSIT,
+      DISTRIBUTE_RESULT_GAS,
+    ))
+  }
+
+  /// Runs the epoch operations of the market with the interest it received
+  #[private]
+  pub fn callback_distribute_interest(
+    &mut self,
+    market_contract: AccountId,
+    passed_time: u64,
+    exchange_rate: D128,
+    atoken_supply: U128,
+    interest_buffer: U128,
+  ) -> Promise {
+    // ft_transfer_call returns the amount the market kept
+    let distributed_interest: Balance = match env::promise_result(0) {
+      PromiseResult::Successful(result) => {
+        near_sdk::serde_json::from_slice::<U128>(&result).unwrap().0
+      }
+      _ => 0,
+    };
+
+    self.internal_update_epoch_state(
+      market_contract,
+      passed_time,
+      exchange_rate,
+      atoken_supply.0,
+      interest_buffer.0,
+      distributed_interest,
+    )
   }
 }
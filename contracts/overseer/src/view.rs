@@ -14,14 +14,43 @@ impl Contract {
     self.config.target_deposit_rate
   }
 
+  pub fn get_price(&self, collateral_token: &AccountId) -> PriceResponse {
+    match self.collection.price_map.get(collateral_token) {
+      Some(price_response) => price_response,
+      None => env::panic(format!("No price for the token {}", collateral_token).as_bytes()),
+    }
+  }
+
+  pub fn get_markets(&self) -> Vec<(AccountId, MarketElem)> {
+    self.collection.market_map.to_vec()
+  }
+
+  /// Collateral rewards `custody_contract` has sent to the interest buffer
+  pub fn get_custody_rewards(&self, custody_contract: AccountId) -> CustodyRewards {
+    self
+      .collection
+      .custody_rewards_map
+      .get(&custody_contract)
+      .unwrap_or_default()
+  }
+
+  pub fn get_epoch_state(&self, market_contract: AccountId) -> EpochState {
+    match self.collection.epoch_state_map.get(&market_contract) {
+      Some(epoch_state) => epoch_state,
+      None => env::panic(format!("The market {} is not registered", market_contract).as_bytes()),
+    }
+  }
+
   pub fn get_borrow_limit(
     &self,
     borrower: AccountId,
-    block_time: Option<BlockHeight>,
+    block_time: Option<u64>,
   ) -> (AccountId, U128) {
     let collaterals = self.get_collateral_map(&borrower);
+    // prices are checked against the current block by default, in seconds
+    let block_time: u64 = block_time.unwrap_or_else(|| env::block_timestamp() / SECOND_TO_NANO);
 
-    let (borrow_limit, _) = self.compute_borrow_limit(&collaterals, block_time);
+    let (borrow_limit, _) = self.compute_borrow_limit(&collaterals, Some(block_time));
 
     (borrower, U128::from(borrow_limit))
   }